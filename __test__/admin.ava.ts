@@ -25,13 +25,13 @@ const runner = Workspace.init(
 );
 
 runner.test("admin only", async (t, { root, tenk, alice }) => {
-  await t.throwsAsync(alice.call(tenk, "update_price", {price: NEAR.parse("1N")}));
+  await t.throwsAsync(alice.call(tenk, "update_price", {price: NEAR.parse("1N")}, {attachedDeposit: "1"}));
 });
 
 runner.test("can add admin", async (t, { root, tenk, alice }) => {
-  await root.call(tenk, "add_admin", {account_id: alice});
+  await root.call(tenk, "add_admin", {account_id: alice}, {attachedDeposit: "1"});
   const price = await costPerToken(tenk, 1);
-  await alice.call(tenk, "update_price", {price: NEAR.parse("1N")});
+  await alice.call(tenk, "update_price", {price: NEAR.parse("1N")}, {attachedDeposit: "1"});
   const newPrice = await costPerToken(tenk,1);
   t.assert(newPrice.eq(NEAR.parse("1N")));
   t.assert(price.lt(newPrice));
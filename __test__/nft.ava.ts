@@ -64,3 +64,17 @@ async function assertXTokens(t, root: NearAccount, tenk, num) {
     await assertXTokens(t, root, tenk, x);
   });
 });
+
+runner.test("batch of ten draws unique ids", async (t, { root, tenk }) => {
+  for (let batch = 0; batch < 2; batch++) {
+    const res = await root.call_raw(
+      tenk,
+      "nft_mint_many",
+      { with_cheddar: false, num: 10 },
+      { attachedDeposit: await totalCost(tenk, 10), gas: MINT_ONE_GAS }
+    );
+    t.true(res.succeeded, [res.Failure, ...res.promiseErrorMessages].join("\n"));
+  }
+  const tokens = await nftTokensForOwner(root, tenk);
+  t.is(new Set(tokens.map((token) => token.token_id)).size, 20);
+});
@@ -37,7 +37,7 @@ async function premint_period<T>(
 ): Promise<T> {
   const public_sale_start = now() + duration * 1_000;
   const args: start_presale_args = { public_sale_start };
-  await root.call(tenk, "start_presale", args);
+  await root.call(tenk, "start_presale", args, { attachedDeposit: "1" });
   const sleepTimer = sleep(1000 * duration);
   const res = await fn();
   await sleepTimer;
@@ -65,10 +65,12 @@ runner.test("premint", async (t, { root, tenk, alice }) => {
     const second_token = await mint_raw(tenk, root);
     t.assert(second_token.succeeded);
 
-    await root.call(tenk, "add_whitelist_accounts", {
-      accounts: [alice],
-      allowance: 2,
-    });
+    await root.call(
+      tenk,
+      "add_whitelist_accounts",
+      { accounts: [alice], allowance: 2 },
+      { attachedDeposit: "1" }
+    );
     await mint(tenk, alice, cost);
     await mint(tenk, alice, cost);
     let last_try = await mint_raw(tenk, alice, cost);
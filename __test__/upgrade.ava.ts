@@ -0,0 +1,120 @@
+import { Workspace } from "near-workspaces-ava";
+import { NEAR, Gas } from "near-units";
+import { execSync } from "child_process";
+import { existsSync, mkdirSync } from "fs";
+import { readFile } from "fs/promises";
+import { join } from "path";
+import { binPath, getTokens, now, totalCost } from "./util";
+
+// Revision with the first version of the state layout, the one `migrate` reads
+const V1_REV = "1b34f4fee3199482c499ce43621c1ba480ad2b2d";
+const V1_DIR = join(__dirname, "..", "target", "tenk_v1");
+const V1_PATH = join(V1_DIR, "target", "wasm32-unknown-unknown", "release", "tenk.wasm");
+
+// Build the first version once, failing the tests if it can't be built
+if (!existsSync(V1_PATH)) {
+  mkdirSync(V1_DIR, { recursive: true });
+  execSync(`git archive ${V1_REV} | tar -x -C ${V1_DIR}`, {
+    cwd: join(__dirname, ".."),
+    stdio: "inherit",
+  });
+  execSync("cargo build --target wasm32-unknown-unknown --release -p tenk", {
+    cwd: V1_DIR,
+    stdio: "inherit",
+  });
+}
+
+const price = NEAR.parse("0.01 N");
+const BATCH = 20;
+const BATCHES = 3;
+
+const runner = Workspace.init(
+  { initialBalance: NEAR.parse("20 N").toString() },
+  async ({ root }) => {
+    const alice = await root.createAccount("alice");
+    const tenk = await root.createAndDeploy("tenk", V1_PATH);
+    await root.call(tenk, "new", {
+      owner_id: root.accountId,
+      metadata: {
+        spec: "nft-1.0.0",
+        name: "TENK NFT",
+        symbol: "TENK",
+        base_uri:
+          "https://bafybeiehqz6vklvxkopg3un3avdtevch4cywuihgxrb4oio2qgxf4764bi.ipfs.dweb.link",
+      },
+      size: 100,
+      sale: { price: price.toJSON(), presale_start: now() },
+      cheddar: root.accountId,
+      cheddar_near: 1,
+      cheddar_discount: 0,
+    });
+    await root.call(tenk, "add_whitelist_accounts", {
+      accounts: [alice.accountId],
+      allowance: 2,
+    });
+    await alice.call(
+      tenk,
+      "nft_mint_one",
+      { with_cheddar: false },
+      { attachedDeposit: NEAR.parse("1 N") }
+    );
+    // enough tokens that going over all of them in `migrate` would show in the gas used
+    for (let i = 0; i < BATCHES; i++) {
+      await root.call(
+        tenk,
+        "nft_mint_many",
+        { with_cheddar: false, num: BATCH },
+        { attachedDeposit: NEAR.parse("2 N"), gas: Gas.parse("300 Tgas") }
+      );
+    }
+    return { tenk, alice };
+  }
+);
+
+runner.test("upgrade from the first version", async (t, { root, tenk, alice }) => {
+  const minted = 1 + BATCH * BATCHES;
+  await tenk
+    .createTransaction(tenk)
+    .deployContract(await readFile(binPath("tenk")))
+    .functionCall("migrate", {}, { gas: Gas.parse("300 Tgas") })
+    .signAndSend();
+
+  t.is(await tenk.view("nft_total_supply"), minted.toString());
+  t.deepEqual(await tenk.view("raffle_stats"), {
+    initial_size: 100,
+    drawn: minted,
+    pending: 0,
+  });
+  t.true(await tenk.view("whitelisted", { account_id: alice.accountId }));
+  t.is(await tenk.view("remaining_allowance", { account_id: alice.accountId }), 1);
+
+  // holders are counted after the upgrade
+  t.is(await tenk.view("num_holders"), null);
+  while (
+    !(await root.call(tenk, "admin_count_holders", { limit: 25 }, { attachedDeposit: "1" }))
+  ) {}
+  t.is(await tenk.view("num_holders"), 2);
+
+  // presale allowance carries over
+  await alice.call(
+    tenk,
+    "nft_mint_one",
+    {},
+    { attachedDeposit: await totalCost(tenk, 1, alice.accountId) }
+  );
+  t.is((await getTokens(tenk, alice)).length, 2);
+  t.is(await tenk.view("remaining_allowance", { account_id: alice.accountId }), 0);
+
+  // ids moved by the draws before the upgrade can be reserved once indexed
+  while (
+    (await root.call(tenk, "admin_index_raffle", { limit: 10 }, { attachedDeposit: "1" })) > 0
+  ) {}
+  const ids: number[] = await tenk.view("remaining_sample", { limit: 5 });
+  await root.call(
+    tenk,
+    "admin_mint_specific",
+    { token_ids: ids, recipient: root.accountId },
+    { attachedDeposit: "1", gas: Gas.parse("300 Tgas") }
+  );
+  t.is(await tenk.view("nft_total_supply"), (minted + 6).toString());
+});
@@ -0,0 +1,117 @@
+//! Allowlist committed to as a Merkle root, so that accounts don't need to be stored upfront.
+//!
+//! Leaves are `sha256("{account_id}:{allowance}")`. Each level hashes the concatenation of the
+//! two sorted child hashes, so a proof is just the list of sibling hashes from leaf to root.
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Mint with an allowance proven against `allowlist_root` instead of the stored whitelist.
+    /// The first mint stores the proven allowance, later mints use what is left of it.
+    #[payable]
+    pub fn nft_mint_with_proof(
+        &mut self,
+        with_cheddar: bool,
+        num: u32,
+        proof: Vec<Base64VecU8>,
+        allowance: u32,
+    ) -> Vec<Token> {
+        let root = self.allowlist_root.expect("No allowlist root is set");
+        let account_id = self.minter_account();
+        require!(
+            verify_proof(&root, &allowlist_leaf(&account_id, allowance), &proof),
+            "Invalid allowlist proof"
+        );
+        if self.whitelist.get(&account_id).is_none() {
+            self.whitelist.insert(&account_id, &allowance);
+        }
+        self.nft_mint_many(with_cheddar, num, None, None)
+    }
+
+    /// Root of the Merkle allowlist, None if not used
+    pub fn allowlist_root(&self) -> Option<Base64VecU8> {
+        self.allowlist_root.map(|root| root.to_vec().into())
+    }
+}
+
+fn allowlist_leaf(account_id: &AccountId, allowance: u32) -> [u8; 32] {
+    hash(format!("{}:{}", account_id, allowance).as_bytes())
+}
+
+fn hash(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&env::sha256(data));
+    hash
+}
+
+fn verify_proof(root: &[u8; 32], leaf: &[u8; 32], proof: &[Base64VecU8]) -> bool {
+    let computed = proof.iter().try_fold(*leaf, |node, sibling| {
+        let sibling = sibling.0.as_slice();
+        if sibling.len() != 32 {
+            return None;
+        }
+        Some(if node.as_slice() <= sibling {
+            hash(&[node.as_slice(), sibling].concat())
+        } else {
+            hash(&[sibling, node.as_slice()].concat())
+        })
+    });
+    computed.as_ref() == Some(root)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b {
+            hash(&[a.as_slice(), b].concat())
+        } else {
+            hash(&[b.as_slice(), a].concat())
+        }
+    }
+
+    /// Allowlist of alice with 2 tokens and bob with 1, returning the contract and alice's proof
+    fn allowlist_contract() -> (Contract, Vec<Base64VecU8>) {
+        let mut contract = new_contract_with_sale(presale());
+        let leaves = [
+            allowlist_leaf(&alice(), 2),
+            allowlist_leaf(&bob(), 1),
+            hash(b"carol.near:3"),
+            hash(b"dave.near:1"),
+        ];
+        let left = parent(&leaves[0], &leaves[1]);
+        let right = parent(&leaves[2], &leaves[3]);
+        contract.admin_set_allowlist_root(Some(parent(&left, &right).to_vec().into()));
+        let proof = vec![leaves[1].to_vec().into(), right.to_vec().into()];
+        (contract, proof)
+    }
+
+    #[test]
+    fn mint_with_proof() {
+        let (mut contract, proof) = allowlist_contract();
+        set_context(&alice(), 3 * PRICE + ONE_NEAR);
+        let tokens = contract.nft_mint_with_proof(false, 3, proof, 2);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(contract.remaining_allowance(&alice()), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid allowlist proof")]
+    fn invalid_proof() {
+        let (mut contract, mut proof) = allowlist_contract();
+        proof.reverse();
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_with_proof(false, 1, proof, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid allowlist proof")]
+    fn tampered_allowance() {
+        let (mut contract, proof) = allowlist_contract();
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_with_proof(false, 1, proof, 5);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::*;
+use near_contract_standards::non_fungible_token::events::NftBurn;
+use near_sdk::assert_one_yocto;
+
+#[near_bindgen]
+impl Contract {
+    /// Burn a token owned by the caller. Burning doesn't lower the number of tokens minted,
+    /// so it doesn't affect the price. Unless `reopen_on_burn` is set, the burned id is gone
+    /// for good and the collection's final supply shrinks by one. Otherwise the id goes back
+    /// to the raffle and can be minted again, which reopens a sold out collection.
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(&token_id)
+            .unwrap_or_else(|| env::panic_str("Token not found"));
+        require!(
+            owner_id == env::predecessor_account_id(),
+            "Only the token owner can burn it"
+        );
+        self.tokens.owner_by_id.remove(&token_id);
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(&token_id);
+        }
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut owner_tokens = tokens_per_owner
+                .get(&owner_id)
+                .unwrap_or_else(|| env::panic_str("Token not found"));
+            owner_tokens.remove(&token_id);
+            if owner_tokens.is_empty() {
+                tokens_per_owner.remove(&owner_id);
+                if self.counts_holder(&owner_id) {
+                    self.num_holders -= 1;
+                }
+            } else {
+                tokens_per_owner.insert(&owner_id, &owner_tokens);
+            }
+        }
+        if let Some(approvals_by_id) = &mut self.tokens.approvals_by_id {
+            approvals_by_id.remove(&token_id);
+        }
+        self.soulbound.remove(&token_id);
+        self.token_royalties.remove(&token_id);
+        self.token_minted_at.remove(&token_id);
+        if self.reopen_on_burn {
+            let id = token_id
+                .parse()
+                .unwrap_or_else(|_| env::panic_str("Token id is not a number"));
+            self.raffle.push(id);
+            self.burns_reopened += 1;
+        }
+        self.assert_supply_invariant();
+        NftBurn {
+            owner_id: &owner_id,
+            token_ids: &[&token_id],
+            authorized_id: None,
+            memo: None,
+        }
+        .emit();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn sold_out_contract(reopen_on_burn: bool) -> (Contract, Vec<Token>) {
+        let mut contract = new_contract();
+        contract.admin_set_reopen_on_burn(reopen_on_burn);
+        let tokens = contract.nft_mint_many_ungaurded(SIZE, &alice(), true, false);
+        assert!(matches!(contract.get_status(), Status::SoldOut));
+        set_context(&alice(), 1);
+        (contract, tokens)
+    }
+
+    #[test]
+    fn burn_stays_sold_out() {
+        let (mut contract, tokens) = sold_out_contract(false);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert!(contract.nft_token(tokens[0].token_id.clone()).is_none());
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, SIZE as u128 - 1);
+        assert_eq!(contract.tokens_left(), 0);
+        assert!(matches!(contract.get_status(), Status::SoldOut));
+    }
+
+    #[test]
+    fn burn_reopens() {
+        let (mut contract, tokens) = sold_out_contract(true);
+        let token_id = tokens[3].token_id.clone();
+        contract.nft_burn(token_id.clone());
+        assert_eq!(contract.tokens_left(), 1);
+        assert!(matches!(contract.get_status(), Status::Open));
+        assert_eq!(contract.initial(), SIZE as u64);
+
+        let token = contract.nft_mint_many_ungaurded(1, &bob(), true, false);
+        assert_eq!(token[0].token_id, token_id);
+        assert!(matches!(contract.get_status(), Status::SoldOut));
+    }
+
+    #[test]
+    fn burn_keeps_total_minted() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        set_context(&alice(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert_eq!(contract.nft_total_supply().0, 2);
+        assert_eq!(contract.nft_total_minted(), 3);
+    }
+
+    #[test]
+    fn supply_invariant() {
+        let mut contract = new_contract();
+        contract.admin_set_reopen_on_burn(true);
+        assert!(contract.supply_invariant_holds());
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        assert!(contract.supply_invariant_holds());
+        set_context(&alice(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert!(contract.supply_invariant_holds());
+        contract.nft_mint_many_ungaurded(SIZE - 2, &bob(), true, false);
+        assert!(contract.supply_invariant_holds());
+
+        contract.pending_tokens = 1;
+        assert!(!contract.supply_invariant_holds());
+        contract.pending_tokens = 0;
+        contract.counter += 1;
+        assert!(!contract.supply_invariant_holds());
+    }
+
+    #[test]
+    fn supply_invariant_burn_without_reopen() {
+        let mut contract = new_contract();
+        contract.admin_extend_raffle(5);
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        contract.pending_tokens = 2;
+        set_context(&alice(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert!(contract.supply_invariant_holds());
+        assert_eq!(contract.initial_size, SIZE + 5);
+        assert_eq!(contract.counter + contract.tokens_left() + 2, SIZE + 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the token owner can burn it")]
+    fn burn_not_owner() {
+        let (mut contract, tokens) = sold_out_contract(false);
+        set_context(&bob(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+    }
+}
@@ -0,0 +1,119 @@
+//! NEP-297 events specific to this contract.
+//!
+//! Standard NEP-171 events (`nft_mint`, `nft_burn`, `nft_transfer`) are mandatory and always
+//! emitted through `near_contract_standards`. The informational events below go through
+//! `Contract::emit_event` and can be turned off with `admin_set_events_enabled`.
+use crate::{Contract, TimestampMs};
+use near_sdk::AccountId;
+use near_sdk::{env, json_types::U128, serde::Serialize, serde_json};
+
+pub const EVENT_STANDARD: &str = "tenk";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+/// Informational event of the `tenk` standard
+pub trait Event: Serialize {
+    const NAME: &'static str;
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a, T: Serialize> {
+    standard: &'static str,
+    version: &'static str,
+    event: &'a str,
+    data: &'a [T],
+}
+
+impl Contract {
+    /// Log `event` unless informational events are disabled
+    pub(crate) fn emit_event<E: Event>(&self, event: E) {
+        if !self.events_enabled {
+            return;
+        }
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_VERSION,
+            event: E::NAME,
+            data: &[event],
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).unwrap_or_else(|_| env::abort())
+        ));
+    }
+}
+
+/// Public sale or presale price was updated by the owner or an admin
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SalePriceChanged {
+    pub price: U128,
+    pub presale_price: Option<U128>,
+}
+
+impl Event for SalePriceChanged {
+    const NAME: &'static str = "sale_price_changed";
+}
+
+/// Contract metadata (`nft_metadata`) was replaced by the owner
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractMetadataUpdated<'a> {
+    pub name: &'a str,
+    pub symbol: &'a str,
+    pub base_uri: Option<&'a str>,
+}
+
+impl Event for ContractMetadataUpdated<'_> {
+    const NAME: &'static str = "contract_metadata_updated";
+}
+
+/// Last token of the collection was minted
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SoldOut {
+    pub sold_out_at: TimestampMs,
+    pub minted: u32,
+}
+
+impl Event for SoldOut {
+    const NAME: &'static str = "sold_out";
+}
+
+/// An account was approved to transfer a token
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftApproved<'a> {
+    pub token_id: &'a str,
+    pub account_id: &'a AccountId,
+    pub approval_id: u64,
+}
+
+impl Event for NftApproved<'_> {
+    const NAME: &'static str = "nft_approve";
+}
+
+/// Approval of `account_id` for a token was revoked, or all approvals if `account_id` is None
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRevoked<'a> {
+    pub token_id: &'a str,
+    pub account_id: Option<&'a AccountId>,
+}
+
+impl Event for NftRevoked<'_> {
+    const NAME: &'static str = "nft_revoke";
+}
+
+/// Cheddar was spent to mint tokens, taking it out of the account's deposit
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CheddarSpent<'a> {
+    pub account_id: &'a AccountId,
+    pub num: u32,
+    pub amount: U128,
+}
+
+impl Event for CheddarSpent<'_> {
+    const NAME: &'static str = "cheddar_spent";
+}
@@ -3,8 +3,9 @@ use near_contract_standards::non_fungible_token::{
     NonFungibleToken, Token, TokenId,
 };
 use near_sdk::{
+    assert_one_yocto,
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::{LazyOption, LookupMap, UnorderedSet},
+    collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet, Vector},
     env, ext_contract,
     json_types::{Base64VecU8, U128},
     log, near_bindgen, require,
@@ -12,29 +13,37 @@ use near_sdk::{
     witgen, AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue,
     PublicKey,
 };
-use near_units::parse_gas;
+use near_units::{parse_gas, parse_near};
 
 /// milliseconds elapsed since the UNIX epoch
 #[witgen]
 type TimestampMs = u64;
 
+mod allowlist;
+mod burn;
+mod events;
 pub mod linkdrop;
+mod migrate;
 mod owner;
 pub mod payout;
 mod raffle;
 mod standards;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test_utils;
 mod types;
 mod user;
 mod util;
 mod views;
 
-// use linkdrop::LINKDROP_DEPOSIT;
+use events::*;
+use linkdrop::{default_linkdrop_contract, LINKDROP_DEPOSIT};
 use payout::*;
 use raffle::Raffle;
 use standards::*;
 use types::*;
-use user::E24;
-use util::{current_time_ms, is_promise_success, log_mint, refund};
+use user::{Deposits, Whitelist, E24};
+use util::{current_time_ms, is_promise_success, log_mint, log_mints, refund};
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
@@ -47,7 +56,7 @@ pub struct Contract {
 
     /// Address of the cheddar token
     cheddar: AccountId,
-    cheddar_deposits: LookupMap<AccountId, u128>,
+    cheddar_deposits: Deposits,
     /// cheddar from convertion expressed in 1e3, including the boost:
     /// amount of cheddar = (amount_near / 1e3) * cheddar_near;
     /// Example. If 1 near = 438 cheddar, then we need to set cheddar_near = 438'000
@@ -57,21 +66,134 @@ pub struct Contract {
 
     // Linkdrop fields will be removed once proxy contract is deployed
     pub accounts: LookupMap<PublicKey, bool>,
-    // Whitelist
-    whitelist: LookupMap<AccountId, u32>,
+    /// Whitelist and remaining allowances
+    whitelist: Whitelist,
 
     sale: Sale,
 
     admins: UnorderedSet<AccountId>,
     counter: u32,
+
+    /// Extension of the token media file, e.g. `png` for `{id}.png`
+    media_extension: String,
+    /// Extension of the token reference file, e.g. `json` for `{id}.json`
+    reference_extension: String,
+    /// Once frozen, the token metadata format can't be changed
+    metadata_frozen: bool,
+    /// Media and reference hashes of tokens, attached to the metadata when minted
+    token_hashes: LookupMap<TokenId, (Base64VecU8, Base64VecU8)>,
+    /// Whether burned token ids go back to the raffle and can be minted again
+    reopen_on_burn: bool,
+    /// Until revealed, tokens are shown with the placeholder media and reference if one is set
+    revealed: bool,
+    placeholder_media: Option<String>,
+    placeholder_reference: Option<String>,
+    /// Whether informational events are emitted. Standard NEP-171 events always are.
+    events_enabled: bool,
+    /// NEAR prepaid by an admin to cover the storage of an account's future mints
+    storage_deposits: LookupMap<AccountId, Balance>,
+    /// Token ids in the order they were taken out of the raffle
+    draw_order: Vector<u32>,
+    /// Fixed seed of the raffle, making the draw order reproducible. Only used on testnet.
+    raffle_seed: Option<Base64VecU8>,
+    /// Contract creating the accounts of claimed linkdrops
+    linkdrop_contract: AccountId,
+    /// NEAR sent along with a claimed linkdrop
+    linkdrop_deposit: Balance,
+    /// JSON attached as `extra` to the metadata of tokens when minted
+    token_extras: LookupMap<TokenId, String>,
+    /// When the last token was minted, the first time the collection sold out
+    sold_out_at: Option<TimestampMs>,
+    /// Whether tokens can be paid with cheddar
+    accepts_cheddar: bool,
+    /// Until then only the owner and admins can transfer tokens, e.g. to prevent flipping before reveal
+    transfers_locked_until: Option<TimestampMs>,
+    /// Number of tokens an account can still mint without paying the price, e.g. giveaway winners
+    free_mint_list: LookupMap<AccountId, u32>,
+    /// Tokens which can't be transferred, e.g. achievement badges
+    soulbound: LookupMap<TokenId, bool>,
+    /// Number of tokens minted during the presale, see `Sale::presale_supply_cap`
+    presale_minted: u32,
+    /// Value in yoctoNEAR of the referral bonuses paid to each referrer
+    referral_earnings: LookupMap<AccountId, Balance>,
+    /// Merkle root of accounts and allowances which can mint with a proof, see `nft_mint_with_proof`
+    allowlist_root: Option<[u8; 32]>,
+    /// Last time each account minted, see `Sale::mint_cooldown_ms`
+    last_mint_at: LookupMap<AccountId, TimestampMs>,
+    /// Royalties of tokens diverging from the collection's `Sale::royalties`
+    token_royalties: LookupMap<TokenId, Royalties>,
+    /// Number of accounts owning at least one token
+    num_holders: u64,
+    /// When each token was minted
+    token_minted_at: LookupMap<TokenId, TimestampMs>,
+    /// `copies` in the metadata of minted tokens, e.g. the collection size for editions
+    copies: Option<u64>,
+    /// Once frozen, the primary sale royalties `Sale::initial_royalties` can't be changed
+    royalties_frozen: bool,
+    /// Emergency freeze: only the owner and admins can mint, transfer or approve tokens
+    frozen: bool,
+    /// yoctoNEAR paid for minted tokens, storage and refunds excluded
+    total_near_raised: Balance,
+    /// Cheddar paid for minted tokens
+    total_cheddar_raised: Balance,
+    /// Last mint of each account sent with a `request_id`, to answer resubmissions
+    mint_requests: LookupMap<AccountId, MintRequest>,
+    /// Use the data URIs of `token_media` as media instead of `{id}.{media_extension}`
+    on_chain_media: bool,
+    /// Media of tokens embedded as data URIs, e.g. small pixel art
+    token_media: LookupMap<TokenId, String>,
+    /// Marketplace approved for each token at mint, so it can be listed right away
+    auto_approve_account: Option<AccountId>,
+    /// `Permission` flags of admins, admins without an entry have them all
+    admin_permissions: LookupMap<AccountId, u8>,
+    /// Sum of `storage_deposits`, NEAR which belongs to the accounts who prepaid it
+    storage_deposits_total: Balance,
+    /// Size of the collection at creation, including the ids added by `admin_extend_raffle`
+    initial_size: u32,
+    /// Number of burned ids put back in the raffle, see `reopen_on_burn`
+    burns_reopened: u32,
+    /// Next raffle index to go over in `admin_index_raffle`, `None` once every moved value knows
+    /// its index. Only set for raffles migrated from the first version.
+    raffle_index_from: Option<u64>,
+    /// Progress of `admin_count_holders`, only set for contracts migrated from the first version
+    holders_count: Option<HoldersCount>,
+    /// Accounts already counted in `num_holders` by `admin_count_holders`
+    counted_holders: UnorderedSet<AccountId>,
 }
 
-// const GAS_REQUIRED_FOR_LINKDROP: Gas = Gas(parse_gas!("40 Tgas") as u64);
-// const GAS_REQUIRED_TO_CREATE_LINKDROP: Gas = Gas(parse_gas!("20 Tgas") as u64);
+const GAS_REQUIRED_FOR_LINKDROP: Gas = Gas(parse_gas!("40 Tgas") as u64);
+const GAS_REQUIRED_TO_CREATE_LINKDROP: Gas = Gas(parse_gas!("20 Tgas") as u64);
 const GAS_FOR_FT_TRANSFER: Gas = Gas(parse_gas!("10 Tgas") as u64);
+const GAS_FOR_WITHDRAW_CALLBACK: Gas = Gas(parse_gas!("5 Tgas") as u64);
 
+/// Refunds of at most this many yoctoNEAR aren't worth a transfer and are kept by the contract
+const REFUND_DUST: Balance = 1;
 const TECH_BACKUP_OWNER: &str = "cheddar.near";
 const MAX_DATE: u64 = 8640000000000000;
+/// Max number of accounts updated in one whitelist call
+const MAX_WHITELIST_BATCH: usize = 200;
+/// Max length in bytes of a token's `extra` metadata
+const MAX_TOKEN_EXTRA_LEN: usize = 1024;
+/// Max length in bytes of a token's on-chain media data URI
+const MAX_TOKEN_MEDIA_LEN: usize = 16 * 1024;
+/// Max number of ids reserved in one call
+const MAX_RESERVE_RANGE: u32 = 100;
+/// Max number of recipients of one airdrop call
+const MAX_AIRDROP_BATCH: usize = 100;
+/// Max number of tokens minted in one call, whatever the sale's `mint_rate_limit`
+const MAX_MINT_BATCH: u32 = 50;
+/// Max number of depositors refunded in one call
+const MAX_REFUND_BATCH: u64 = 50;
+/// Max number of raffle indexes gone over in one `admin_index_raffle` call
+const MAX_RAFFLE_INDEX_BATCH: u64 = 500;
+/// Max number of tokens or accounts gone over in one `admin_count_holders` call
+const MAX_HOLDERS_COUNT_BATCH: u64 = 100;
+/// Max length in bytes of the `request_id` of a mint
+const MAX_REQUEST_ID_LEN: usize = 64;
+/// How long in milliseconds a mint with the same `request_id` returns the tokens already minted
+const MINT_REQUEST_WINDOW_MS: u64 = 10 * 60 * 1000;
+/// How far in the past a rescheduled sale start may be
+const SALE_START_TOLERANCE_MS: u64 = 5 * 60 * 1000;
 // const GAS_REQUIRED_FOR_LINKDROP_CALL: Gas = Gas(5_000_000_000_000);
 
 #[ext_contract(ext_self)]
@@ -86,6 +208,8 @@ trait Linkdrop {
     fn on_send_with_callback(&mut self) -> Promise;
 
     fn link_callback(&mut self, account_id: AccountId, mint_for_free: bool) -> Token;
+
+    fn on_withdraw_cheddar(&mut self, account_id: AccountId, amount: U128);
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
@@ -97,9 +221,43 @@ enum StorageKey {
     Approval,
     Raffle,
     LinkdropKeys,
+    /// Whitelist of the first version, still read by contracts migrated from it
     Whitelist,
     Admins,
     CheddarDeposits,
+    TokenHashes,
+    StorageDeposits,
+    DrawOrder,
+    TokenExtras,
+    WhitelistEntries,
+    FreeMintList,
+    Soulbound,
+    ReferralEarnings,
+    LastMintAt,
+    TokenRoyalties,
+    TokenMintedAt,
+    MintRequests,
+    CheddarDepositors,
+    TokenMedia,
+    AdminPermissions,
+    CountedHolders,
+}
+
+/// Amounts of a mint, see `MintResult`
+#[derive(Default)]
+struct Charge {
+    charged: Balance,
+    refunded: Balance,
+    storage_cost: Balance,
+}
+
+/// Account paying for a mint, with where its leftover deposit goes, who referred it and the
+/// `request_id` to record the mint under
+struct Payer<'a> {
+    account_id: &'a AccountId,
+    refund_to: &'a AccountId,
+    referrer: Option<&'a AccountId>,
+    request_id: Option<&'a str>,
 }
 
 #[near_bindgen]
@@ -139,12 +297,11 @@ impl Contract {
         cheddar_near: u32,
         cheddar_discount: u32,
     ) -> Self {
+        require!(size > 0, "collection size must be positive");
         metadata.assert_valid();
         sale.validate();
-        require!(
-            cheddar_discount < 100,
-            "cheddar discount can't be more than 100%"
-        );
+        assert_cheddar_near(cheddar_near);
+        assert_cheddar_discount(cheddar_discount);
         Self {
             tokens: NonFungibleToken::new(
                 StorageKey::NonFungibleToken,
@@ -159,30 +316,159 @@ impl Contract {
             cheddar,
             cheddar_near: cheddar_near.into(),
             cheddar_boost: 100 - cheddar_discount,
-            cheddar_deposits: LookupMap::new(StorageKey::CheddarDeposits),
+            cheddar_deposits: Deposits::new(LookupMap::new(StorageKey::CheddarDeposits)),
             accounts: LookupMap::new(StorageKey::LinkdropKeys),
-            whitelist: LookupMap::new(StorageKey::Whitelist),
+            whitelist: Whitelist::new(LookupMap::new(StorageKey::Whitelist)),
             sale,
             admins: UnorderedSet::new(StorageKey::Admins),
             counter: 0,
+            media_extension: "png".to_string(),
+            reference_extension: "json".to_string(),
+            metadata_frozen: false,
+            token_hashes: LookupMap::new(StorageKey::TokenHashes),
+            reopen_on_burn: false,
+            revealed: false,
+            placeholder_media: None,
+            placeholder_reference: None,
+            events_enabled: true,
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            draw_order: Vector::new(StorageKey::DrawOrder),
+            raffle_seed: None,
+            linkdrop_contract: default_linkdrop_contract(),
+            linkdrop_deposit: LINKDROP_DEPOSIT,
+            token_extras: LookupMap::new(StorageKey::TokenExtras),
+            sold_out_at: None,
+            accepts_cheddar: true,
+            transfers_locked_until: None,
+            free_mint_list: LookupMap::new(StorageKey::FreeMintList),
+            soulbound: LookupMap::new(StorageKey::Soulbound),
+            presale_minted: 0,
+            referral_earnings: LookupMap::new(StorageKey::ReferralEarnings),
+            allowlist_root: None,
+            last_mint_at: LookupMap::new(StorageKey::LastMintAt),
+            token_royalties: LookupMap::new(StorageKey::TokenRoyalties),
+            num_holders: 0,
+            token_minted_at: LookupMap::new(StorageKey::TokenMintedAt),
+            copies: None,
+            royalties_frozen: false,
+            frozen: false,
+            total_near_raised: 0,
+            total_cheddar_raised: 0,
+            mint_requests: LookupMap::new(StorageKey::MintRequests),
+            on_chain_media: false,
+            token_media: LookupMap::new(StorageKey::TokenMedia),
+            auto_approve_account: None,
+            admin_permissions: LookupMap::new(StorageKey::AdminPermissions),
+            storage_deposits_total: 0,
+            initial_size: size,
+            burns_reopened: 0,
+            raffle_index_from: None,
+            holders_count: None,
+            counted_holders: UnorderedSet::new(StorageKey::CountedHolders),
         }
     }
 
     #[payable]
     pub fn nft_mint_one(&mut self, with_cheddar: bool) -> Token {
-        self.nft_mint_many(with_cheddar, 1)[0].clone()
+        self.nft_mint_many(with_cheddar, 1, None, None)[0].clone()
     }
 
+    /// Mint `num` tokens. `referrer` gets `Sale::referral_bps` of the price.
+    /// A transaction resubmitted with the same `request_id` within `MINT_REQUEST_WINDOW_MS`
+    /// returns the tokens minted the first time and refunds the deposit.
+    /// Only the last request of an account is kept, the minter pays for its storage. An expired
+    /// request is removed by the next mint of the account.
     #[payable]
-    pub fn nft_mint_many(&mut self, with_cheddar: bool, num: u32) -> Vec<Token> {
+    pub fn nft_mint_many(
+        &mut self,
+        with_cheddar: bool,
+        num: u32,
+        referrer: Option<AccountId>,
+        request_id: Option<String>,
+    ) -> Vec<Token> {
+        let account_id = self.minter_account();
+        if let Some(request_id) = &request_id {
+            require!(
+                request_id.len() <= MAX_REQUEST_ID_LEN,
+                format!(
+                    "request_id can't be longer than {} bytes",
+                    MAX_REQUEST_ID_LEN
+                )
+            );
+            if let Some(tokens) = self.repeated_mint_request(&account_id, request_id) {
+                log!("Request {} was already minted", request_id);
+                refund(&account_id, env::attached_deposit());
+                return tokens;
+            }
+        }
+        self.prune_mint_request(&account_id, request_id.is_some());
+        let payer = Payer {
+            account_id: &account_id,
+            refund_to: &account_id,
+            referrer: referrer.as_ref(),
+            request_id: request_id.as_deref(),
+        };
+        self.mint_checked(num, &payer, &account_id, with_cheddar)
+            .tokens
+    }
+
+    /// Mint tokens to `receiver_id`, e.g. as a gift. The minter pays and uses their allowance,
+    /// NEAR left after paying is sent to `refund_to`, by default the minter.
+    #[payable]
+    pub fn nft_mint_to(
+        &mut self,
+        receiver_id: AccountId,
+        with_cheddar: bool,
+        num: u32,
+        refund_to: Option<AccountId>,
+        referrer: Option<AccountId>,
+    ) -> Vec<Token> {
+        let account_id = &self.minter_account();
+        let payer = Payer {
+            account_id,
+            refund_to: refund_to.as_ref().unwrap_or(account_id),
+            referrer: referrer.as_ref(),
+            request_id: None,
+        };
+        self.mint_checked(num, &payer, &receiver_id, with_cheddar)
+            .tokens
+    }
+
+    /// Mint `num` tokens like `nft_mint_many`, also returning what was charged, refunded and
+    /// paid for storage. When paying with NEAR these add up to the attached deposit, unless the
+    /// refund is too small to be sent.
+    #[payable]
+    pub fn nft_mint_many_detailed(&mut self, with_cheddar: bool, num: u32) -> MintResult {
+        let account_id = &self.minter_account();
+        let payer = Payer {
+            account_id,
+            refund_to: account_id,
+            referrer: None,
+            request_id: None,
+        };
+        self.mint_checked(num, &payer, account_id, with_cheddar)
+    }
+
+    /// Mint `num` tokens to `receiver_id` within the sale's limits, charging `payer` and using
+    /// their allowance.
+    fn mint_checked(
+        &mut self,
+        num: u32,
+        payer: &Payer,
+        receiver_id: &AccountId,
+        with_cheddar: bool,
+    ) -> MintResult {
+        require!(
+            num <= MAX_MINT_BATCH,
+            format!("Cannot mint more than {} per transaction", MAX_MINT_BATCH)
+        );
         if let Some(limit) = self.sale.mint_rate_limit {
             require!(num <= limit, "over mint limit");
         }
-        let owner_id = &env::signer_account_id();
-        let num = self.assert_can_mint(owner_id, num);
-        let tokens = self.nft_mint_many_ungaurded(num, owner_id, false, with_cheddar);
-        self.use_whitelist_allowance(owner_id, num);
-        tokens
+        let num = self.assert_can_mint(payer.account_id, num);
+        let result = self.mint_for(num, payer, receiver_id, false, with_cheddar);
+        self.use_whitelist_allowance(payer.account_id, result.tokens.len() as u32);
+        result
     }
 
     fn nft_mint_many_ungaurded(
@@ -192,6 +478,27 @@ impl Contract {
         mint_for_free: bool,
         with_cheddar: bool,
     ) -> Vec<Token> {
+        let payer = Payer {
+            account_id: user,
+            refund_to: user,
+            referrer: None,
+            request_id: None,
+        };
+        self.mint_for(num, &payer, user, mint_for_free, with_cheddar)
+            .tokens
+    }
+
+    /// Mint `num` tokens to `receiver_id`, charging `payer` unless `mint_for_free`.
+    /// If the tokens run out midway only the minted ones are charged, the rest of the deposit
+    /// is refunded.
+    fn mint_for(
+        &mut self,
+        num: u32,
+        payer: &Payer,
+        receiver_id: &AccountId,
+        mint_for_free: bool,
+        with_cheddar: bool,
+    ) -> MintResult {
         let initial_storage_usage = if mint_for_free {
             0
         } else {
@@ -199,77 +506,185 @@ impl Contract {
         };
 
         // Mint tokens
-        let tokens: Vec<Token> = (0..num)
-            .map(|_| self.draw_and_mint(user.clone(), None))
+        let ids = self.raffle.draw_many(
+            u32::min(num, self.tokens_left()) as u64,
+            self.raffle_seed.as_ref().map(|seed| seed.0.as_slice()),
+        );
+        let tokens: Vec<Token> = ids
+            .into_iter()
+            .map(|id| self.mint_drawn(id, receiver_id.clone(), None))
             .collect();
+        let minted = tokens.len() as u32;
+        if minted < num {
+            log!("Only {} of {} tokens were left to mint", minted, num);
+        }
+        // recorded before measuring the storage used, so that the payer pays for it
+        if let Some(request_id) = payer.request_id {
+            let request = MintRequest {
+                request_id: request_id.to_string(),
+                at: current_time_ms(),
+                token_ids: tokens.iter().map(|token| token.token_id.clone()).collect(),
+            };
+            self.mint_requests.insert(payer.account_id, &request);
+        }
 
-        if !mint_for_free {
+        let charge = if mint_for_free {
+            Charge::default()
+        } else {
             let storage_used = env::storage_usage() - initial_storage_usage;
-            self.charge_user(num, user, with_cheddar, storage_used);
-        }
-        self.counter += num;
+            self.charge_user(minted, payer, with_cheddar, storage_used)
+        };
+        self.increase_counter(minted);
         // Emit mint event log
-        log_mint(user, &tokens);
-        tokens
+        log_mint(receiver_id, &tokens);
+        MintResult {
+            tokens,
+            charged: charge.charged.into(),
+            refunded: charge.refunded.into(),
+            storage_cost: charge.storage_cost.into(),
+        }
     }
 
-    fn charge_user(&mut self, num: u32, user: &AccountId, with_cheddar: bool, storage_used: u64) {
-        let storage_cost = env::storage_byte_cost() * storage_used as Balance;
-        let near_left = env::attached_deposit() - storage_cost;
+    fn charge_user(
+        &mut self,
+        num: u32,
+        payer: &Payer,
+        with_cheddar: bool,
+        storage_used: u64,
+    ) -> Charge {
+        let user = payer.account_id;
+        require!(
+            !with_cheddar || self.accepts_cheddar,
+            "Paying with cheddar is not accepted"
+        );
+        let mut storage_cost = env::storage_byte_cost() * storage_used as Balance;
+        if let Some(prepaid) = self.storage_deposits.get(user) {
+            let used = u128::min(prepaid, storage_cost);
+            storage_cost -= used;
+            self.storage_deposits_total = self.storage_deposits_total.saturating_sub(used);
+            if prepaid == used {
+                self.storage_deposits.remove(user);
+            } else {
+                self.storage_deposits.insert(user, &(prepaid - used));
+            }
+        }
+        let attached_deposit = env::attached_deposit();
+        require!(
+            attached_deposit >= storage_cost,
+            format!(
+                "Attached deposit does not cover storage, missing {} yoctoNEAR",
+                storage_cost.saturating_sub(attached_deposit)
+            )
+        );
+        let near_left = attached_deposit - storage_cost;
 
         let deposit = if with_cheddar {
             self.cheddar_deposits.get(user).unwrap_or_default()
         } else {
             near_left
         };
-        let cost = self.total_cost(num, user, with_cheddar).0;
+        let near_cost = self.minting_cost(user, num).0;
+        let cost = if with_cheddar {
+            self.cheddar_cost(near_cost)
+        } else {
+            near_cost
+        };
         require!(deposit >= cost, "Not enough deposit to buy");
+        if let Some(multiplier) = self.sale.max_overpay_multiplier {
+            let needed = if with_cheddar { 0 } else { cost } + storage_cost;
+            require!(
+                attached_deposit <= REFUND_DUST
+                    || attached_deposit <= needed.saturating_mul(multiplier as u128),
+                format!("Attached deposit is more than {}x the cost", multiplier)
+            );
+        }
+        self.use_free_mints(user, num);
 
-        let mut refund_near = if with_cheddar {
-            near_left
+        // without royalties there is no one to pay, so the full cost stays with the user
+        let charged = if self.sale.initial_royalties.is_some() {
+            cost
         } else {
-            near_left - cost
+            log!("Royalities are not defined: user is not charged");
+            0
         };
         if with_cheddar {
-            let new_deposit = deposit - cost;
+            self.total_cheddar_raised += charged;
+        } else {
+            self.total_near_raised += charged;
+        }
+        let refund_near = if with_cheddar {
+            near_left
+        } else {
+            near_left - charged
+        };
+        if with_cheddar && charged > 0 {
+            let new_deposit = deposit - charged;
             if new_deposit == 0 {
                 self.cheddar_deposits.remove(&user);
             } else {
                 self.cheddar_deposits.insert(user, &new_deposit);
             }
+            self.emit_event(CheddarSpent {
+                account_id: user,
+                num,
+                amount: charged.into(),
+            });
         }
 
+        let mut to_royalties = charged;
+        if let (Some(referrer), Some(bps)) = (payer.referrer, self.sale.referral_bps) {
+            require!(referrer != user, "Can't refer yourself");
+            if charged > 0 && !self.is_owner(referrer) {
+                let share = charged * bps as u128 / 10_000;
+                to_royalties -= share;
+                Payout::to_account(referrer.clone(), share, with_cheddar)
+                    .send_funds(&mut self.cheddar_deposits);
+                let earnings = self.referral_earnings.get(referrer).unwrap_or_default()
+                    + near_cost * bps as u128 / 10_000;
+                self.referral_earnings.insert(referrer, &earnings);
+            }
+        }
         if let Some(royalties) = &self.sale.initial_royalties {
             royalties.send_funds(
-                cost,
+                to_royalties,
                 &self.tokens.owner_id,
                 with_cheddar,
                 &mut self.cheddar_deposits,
             );
-        } else {
-            log!("Royalities are not defined: user is not charged");
-            if !with_cheddar {
-                refund_near += cost;
-            }
         }
-        if refund_near > 1 {
-            Promise::new(user.clone()).transfer(refund_near);
+        let refunded = if refund_near > REFUND_DUST {
+            Promise::new(payer.refund_to.clone()).transfer(refund_near);
+            refund_near
+        } else {
+            0
+        };
+        Charge {
+            charged,
+            refunded,
+            storage_cost,
         }
     }
 
     // admin methods
 
     /// update the cheddar_near convertion
+    #[payable]
     pub fn admin_set_cheddar_near(&mut self, cheddar_near: u32) {
-        self.assert_owner_or_admin();
-        require!(cheddar_near > 0, "cheddar_near must be positive");
-        require!(
-            cheddar_near > 100,
-            "1 cheddar is rather worth less than 10NEAR"
-        );
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        assert_cheddar_near(cheddar_near);
         self.cheddar_near = cheddar_near as u128;
     }
 
+    /// update the discount (in %) when paying with cheddar
+    #[payable]
+    pub fn admin_set_cheddar_discount(&mut self, cheddar_discount: u32) {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        assert_cheddar_discount(cheddar_discount);
+        self.cheddar_boost = 100 - cheddar_discount;
+    }
+
     // Contract private methods
 
     #[private]
@@ -301,24 +716,131 @@ impl Contract {
         let mut num = num;
         // Check quantity
         // Owner can mint for free
+        let mut presale = false;
         if !self.is_owner(account_id) {
+            require!(
+                !self.frozen || self.is_admin(account_id),
+                "Contract is frozen"
+            );
             let allowance = match self.get_status() {
                 Status::SoldOut => env::panic_str("No NFTs left to mint"),
+                Status::Ended => env::panic_str("Sale has ended"),
                 Status::Closed => env::panic_str("Contract currently closed"),
-                Status::Presale => self.get_whitelist_allowance(account_id),
+                Status::Presale => {
+                    presale = true;
+                    let presale_left = self.presale_tokens_left();
+                    require!(presale_left > 0, "Presale supply cap reached");
+                    u32::min(self.get_whitelist_allowance(account_id), presale_left)
+                }
                 Status::Open => self.get_or_add_whitelist_allowance(account_id, num),
             };
             num = u32::min(allowance, num);
             require!(num > 0, "Account has no more allowance left");
+            self.assert_mint_cooldown(account_id);
         }
         let left = self.tokens_left();
         require!(
             left >= num,
             format!("Not NFTs left to mint, remaining nfts: {}", left)
         );
+        if presale {
+            self.presale_minted += num;
+        }
         num
     }
 
+    /// Reject the mint if the account minted less than `mint_cooldown_ms` ago, else record it
+    fn assert_mint_cooldown(&mut self, account_id: &AccountId) {
+        if let Some(cooldown) = self.sale.mint_cooldown_ms {
+            let now = current_time_ms();
+            if let Some(last) = self.last_mint_at.get(account_id) {
+                let remaining = cooldown.saturating_sub(now.saturating_sub(last));
+                require!(
+                    remaining == 0,
+                    format!(
+                        "Mint cooldown, try again in {} seconds",
+                        remaining / 1000 + u64::from(remaining % 1000 > 0)
+                    )
+                );
+            }
+            self.last_mint_at.insert(account_id, &now);
+        }
+    }
+
+    /// Account minting, paying and receiving the tokens.
+    /// By default it's the signer, so a contract relaying the call mints for the user who signed
+    /// the transaction, using the deposit attached by the contract. That can't be told apart from
+    /// a contract minting on the signer's behalf without consent, and it breaks with
+    /// meta-transactions where the signer is a relayer. With `use_predecessor` the immediate
+    /// caller mints instead, so a relaying contract gets the tokens and refunds and must forward them.
+    fn minter_account(&self) -> AccountId {
+        if self.sale.use_predecessor {
+            env::predecessor_account_id()
+        } else {
+            env::signer_account_id()
+        }
+    }
+
+    fn assert_transferable(&self, token_id: &TokenId) {
+        require!(
+            !self.soulbound.contains_key(token_id),
+            "Token is soulbound and can't be transferred"
+        );
+    }
+
+    fn assert_transfers_unlocked(&self) {
+        if let Some(until) = self.transfers_locked_until {
+            if current_time_ms() < until {
+                let sender = env::predecessor_account_id();
+                require!(
+                    self.is_owner(&sender) || self.is_admin(&sender),
+                    "Transfers locked"
+                );
+            }
+        }
+    }
+
+    /// Remove the last request of `account_id` if it expired, or in any case if it's about to
+    /// be replaced. Done before minting, so the storage freed doesn't offset the storage charged.
+    fn prune_mint_request(&mut self, account_id: &AccountId, replaced: bool) {
+        if let Some(request) = self.mint_requests.get(account_id) {
+            if replaced || current_time_ms() >= request.at + MINT_REQUEST_WINDOW_MS {
+                self.mint_requests.remove(account_id);
+            }
+        }
+    }
+
+    /// Tokens minted by `account_id`'s last request if it was `request_id` and isn't too old
+    fn repeated_mint_request(
+        &self,
+        account_id: &AccountId,
+        request_id: &str,
+    ) -> Option<Vec<Token>> {
+        let request = self.mint_requests.get(account_id)?;
+        if request.request_id != request_id
+            || current_time_ms() >= request.at + MINT_REQUEST_WINDOW_MS
+        {
+            return None;
+        }
+        Some(
+            request
+                .token_ids
+                .into_iter()
+                .filter_map(|token_id| self.nft_token(token_id))
+                .collect(),
+        )
+    }
+
+    fn assert_not_frozen(&self) {
+        if self.frozen {
+            let sender = env::predecessor_account_id();
+            require!(
+                self.is_owner(&sender) || self.is_admin(&sender),
+                "Contract is frozen"
+            );
+        }
+    }
+
     fn assert_owner(&self) {
         require!(self.signer_is_owner(), "Method is private to owner")
     }
@@ -338,6 +860,16 @@ impl Contract {
         )
     }
 
+    /// Like `assert_owner_or_admin`, also requiring admins to have `permission`
+    fn assert_admin_permission(&self, permission: u8) {
+        self.assert_owner_or_admin();
+        let signer = env::signer_account_id();
+        require!(
+            self.is_owner(&signer) || self.admin_permissions(signer) & permission == permission,
+            "Admin doesn't have the permission to call this method"
+        );
+    }
+
     #[allow(dead_code)]
     fn signer_is_admin(&self) -> bool {
         self.is_admin(&env::signer_account_id())
@@ -352,18 +884,78 @@ impl Contract {
         self.admins.contains(&account_id)
     }
 
-    /*
-        fn full_link_price(&self, minter: &AccountId) -> u128 {
-            LINKDROP_DEPOSIT
-                + if self.is_owner(minter) {
-                    parse_near!("0 mN")
-                } else {
-                    parse_near!("8 mN")
-                }
+    fn full_link_price(&self, minter: &AccountId) -> u128 {
+        self.linkdrop_deposit
+            + if self.is_owner(minter) {
+                parse_near!("0 mN")
+            } else {
+                parse_near!("8 mN")
+            }
+    }
+
+    /// Count `num` more minted tokens and record when the collection sells out.
+    /// `counter` drives the price, so it must not wrap.
+    fn increase_counter(&mut self, num: u32) {
+        self.counter = self
+            .counter
+            .checked_add(num)
+            .expect("Minted tokens counter overflow");
+        if self.sold_out_at.is_none() && self.tokens_left() == 0 {
+            let sold_out_at = current_time_ms();
+            self.sold_out_at = Some(sold_out_at);
+            self.emit_event(SoldOut {
+                sold_out_at,
+                minted: self.counter,
+            });
+        }
+        self.assert_supply_invariant();
+    }
+
+    /// Whether the supply accounting is consistent: every id of the collection was either
+    /// minted or is still in the raffle, counting pending linkdrops and ids reopened by burns,
+    /// i.e. `counter + tokens_left + pending_tokens == initial_size + burns_reopened`.
+    /// Burns keep `counter`, so it can be higher than the supply.
+    fn supply_invariant_holds(&self) -> bool {
+        self.pending_tokens as u64 <= self.raffle.len()
+            && self.counter as u64 + self.raffle.len()
+                == self.initial_size as u64 + self.burns_reopened as u64
+            && self.nft_total_supply().0 <= self.counter as u128
+    }
+
+    /// Check `supply_invariant_holds` after changes to the supply when built with the
+    /// `paranoid` feature, to catch raffle, pending and counter bugs early
+    fn assert_supply_invariant(&self) {
+        if cfg!(feature = "paranoid") {
+            require!(
+                self.supply_invariant_holds(),
+                "Supply accounting is inconsistent"
+            );
+        }
+    }
+
+    /// Mint a token drawn from the raffle, None if no tokens are left to draw
+    fn draw_and_mint(
+        &mut self,
+        token_owner_id: AccountId,
+        refund: Option<AccountId>,
+    ) -> Option<Token> {
+        if self.tokens_left() == 0 {
+            return None;
         }
-    */
-    fn draw_and_mint(&mut self, token_owner_id: AccountId, refund: Option<AccountId>) -> Token {
-        let id = self.raffle.draw();
+        let id = self
+            .raffle
+            .draw(self.raffle_seed.as_ref().map(|seed| seed.0.as_slice()));
+        Some(self.mint_drawn(id, token_owner_id, refund))
+    }
+
+    /// Mint the token `id` just drawn from the raffle
+    fn mint_drawn(
+        &mut self,
+        id: u64,
+        token_owner_id: AccountId,
+        refund: Option<AccountId>,
+    ) -> Token {
+        self.draw_order.push(&(id as u32));
         self.internal_mint(id.to_string(), token_owner_id, refund)
     }
 
@@ -374,28 +966,93 @@ impl Contract {
         refund_id: Option<AccountId>,
     ) -> Token {
         let token_metadata = Some(self.create_metadata(&token_id));
-        self.tokens
-            .internal_mint_with_refund(token_id, token_owner_id, token_metadata, refund_id)
+        self.token_minted_at.insert(&token_id, &current_time_ms());
+        let accounts = [token_owner_id.clone()];
+        let mut token = self.track_holders(&accounts, |contract| {
+            contract.tokens.internal_mint_with_refund(
+                token_id,
+                token_owner_id,
+                token_metadata,
+                refund_id,
+            )
+        });
+        if let Some(account_id) = self.auto_approve_account.clone() {
+            self.approve_at_mint(&mut token, account_id);
+        }
+        token
+    }
+
+    /// Approve `account_id` for the just minted `token`, as `nft_approve` would
+    fn approve_at_mint(&mut self, token: &mut Token, account_id: AccountId) {
+        let (approvals_by_id, next_approval_id_by_id) = match (
+            self.tokens.approvals_by_id.as_mut(),
+            self.tokens.next_approval_id_by_id.as_mut(),
+        ) {
+            (Some(approvals_by_id), Some(next_approval_id_by_id)) => {
+                (approvals_by_id, next_approval_id_by_id)
+            }
+            _ => return,
+        };
+        let approval_id = next_approval_id_by_id.get(&token.token_id).unwrap_or(1);
+        let mut approvals = approvals_by_id.get(&token.token_id).unwrap_or_default();
+        approvals.insert(account_id.clone(), approval_id);
+        approvals_by_id.insert(&token.token_id, &approvals);
+        next_approval_id_by_id.insert(&token.token_id, &(approval_id + 1));
+        self.emit_event(NftApproved {
+            token_id: &token.token_id,
+            account_id: &account_id,
+            approval_id,
+        });
+        token.approved_account_ids = Some(approvals);
     }
 
     fn create_metadata(&mut self, token_id: &str) -> TokenMetadata {
-        let media = Some(format!("{}.png", token_id));
-        let reference = Some(format!("{}.json", token_id));
+        let on_chain_media = if self.on_chain_media {
+            self.token_media.get(&token_id.to_string())
+        } else {
+            None
+        };
+        let media = Some(
+            on_chain_media.unwrap_or_else(|| format!("{}.{}", token_id, self.media_extension)),
+        );
+        let reference = Some(format!("{}.{}", token_id, self.reference_extension));
         let title = Some(token_id.to_string());
+        let extra = self.token_extras.get(&token_id.to_string());
+        let (media_hash, reference_hash) = self
+            .token_hashes
+            .get(&token_id.to_string())
+            .map_or((None, None), |(media, reference)| {
+                (Some(media), Some(reference))
+            });
         TokenMetadata {
             title, // ex. "Arch Nemesis: Mail Carrier" or "Parcel #5055"
             media, // URL to associated media, preferably to decentralized, content-addressed storage
             issued_at: Some(env::block_timestamp().to_string()), // ISO 8601 datetime when token was issued or minted
-            reference,            // URL to an off-chain JSON file with more info.
-            description: None,    // free-form description
-            media_hash: None, // Base64-encoded sha256 hash of content referenced by the `media` field. Required if `media` is included.
-            copies: None, // number of copies of this set of metadata in existence when token was minted.
-            expires_at: None, // ISO 8601 datetime when token expires
-            starts_at: None, // ISO 8601 datetime when token starts being valid
-            updated_at: None, // ISO 8601 datetime when token was last updated
-            extra: None, // anything extra the NFT wants to store on-chain. Can be stringified JSON.
-            reference_hash: None, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
+            reference,           // URL to an off-chain JSON file with more info.
+            description: None,   // free-form description
+            media_hash, // Base64-encoded sha256 hash of content referenced by the `media` field. Required if `media` is included.
+            copies: self.copies, // number of copies of this set of metadata in existence when token was minted.
+            expires_at: None,    // ISO 8601 datetime when token expires
+            starts_at: None,     // ISO 8601 datetime when token starts being valid
+            updated_at: None,    // ISO 8601 datetime when token was last updated
+            extra, // anything extra the NFT wants to store on-chain. Can be stringified JSON.
+            reference_hash, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
+        }
+    }
+
+    /// Replace media and reference with the placeholder until the collection is revealed.
+    /// The real metadata is stored at mint, so revealing doesn't require any migration.
+    fn with_placeholder(&self, mut token: Token) -> Token {
+        if self.revealed || self.placeholder_media.is_none() {
+            return token;
+        }
+        if let Some(metadata) = token.metadata.as_mut() {
+            metadata.media = self.placeholder_media.clone();
+            metadata.media_hash = None;
+            metadata.reference = self.placeholder_reference.clone();
+            metadata.reference_hash = None;
         }
+        token
     }
 
     fn use_whitelist_allowance(&mut self, account_id: &AccountId, num: u32) {
@@ -421,6 +1078,16 @@ impl Contract {
             })
         })
     }
+    /// Use up to `num` of the account's free mints
+    fn use_free_mints(&mut self, account_id: &AccountId, num: u32) {
+        let free_mints = self.free_mints_of(account_id);
+        if free_mints > num {
+            self.free_mint_list.insert(account_id, &(free_mints - num));
+        } else if free_mints > 0 {
+            self.free_mint_list.remove(account_id);
+        }
+    }
+
     fn has_allowance(&self) -> bool {
         self.sale.allowance.is_some() || self.is_presale()
     }
@@ -434,29 +1101,76 @@ impl Contract {
             return Status::SoldOut;
         }
         let current_time = current_time_ms();
+        if matches!(self.sale.public_sale_end, Some(end) if end < current_time) {
+            return Status::Ended;
+        }
         match (self.sale.presale_start, self.sale.public_sale_start) {
-            (_, Some(public)) if public < current_time => Status::Open,
-            (Some(pre), _) if pre < current_time => Status::Presale,
+            (_, Some(public)) if public <= current_time => Status::Open,
+            (Some(pre), _) if pre <= current_time => Status::Presale,
             (_, _) => Status::Closed,
         }
     }
 
-    fn price(&self, num: u32) -> u128 {
-        let p = match self.get_status() {
+    /// Convert a NEAR amount to cheddar, applying the cheddar boost
+    fn cheddar_cost(&self, near_amount: u128) -> u128 {
+        near_amount / 1000 * self.cheddar_near / 100 * self.cheddar_boost as u128
+    }
+
+    /// Apply the holder discount to `cost` if `minter` already holds a token
+    fn apply_holder_discount(&self, minter: &AccountId, cost: u128) -> u128 {
+        match self.sale.holder_discount_bps {
+            Some(discount) if self.nft_supply_for_owner(minter.clone()).0 > 0 => {
+                cost - cost * discount as u128 / 10_000
+            }
+            _ => cost,
+        }
+    }
+
+    /// Price of the first generation in the current sale
+    fn start_price(&self) -> u128 {
+        match self.get_status() {
             Status::Presale | Status::Closed => self.sale.presale_price.unwrap_or(self.sale.price),
-            Status::Open | Status::SoldOut => self.sale.price,
-        };
-        compute_price(self.counter, num, p.0)
+            Status::Open | Status::SoldOut | Status::Ended => self.sale.price,
+        }
+        .0
+    }
+
+    fn price(&self, num: u32) -> u128 {
+        compute_price(self.counter, num, self.start_price())
     }
 }
 
-fn compute_price(counter: u32, num: u32, start_price: u128) -> u128 {
-    // now we calculate the increased price based on generation.
-    // gen_0: 555
-    // each next gen is 100 nfts and cost +1 NEAR
-    const GEN0: u32 = 555;
-    const GEN_NEXT: u32 = 100;
+fn assert_cheddar_near(cheddar_near: u32) {
+    require!(cheddar_near > 0, "cheddar_near must be positive");
+    require!(
+        cheddar_near > 100,
+        "1 cheddar is rather worth less than 10NEAR"
+    );
+}
 
+fn assert_cheddar_discount(cheddar_discount: u32) {
+    require!(
+        cheddar_discount < 100,
+        "cheddar discount can't be more than 100%"
+    );
+}
+
+// now we calculate the increased price based on generation.
+// gen_0: 555
+// each next gen is 100 nfts and cost +1 NEAR
+const GEN0: u32 = 555;
+const GEN_NEXT: u32 = 100;
+
+/// Price of the token minted after `counter` tokens
+fn compute_unit_price(counter: u32, start_price: u128) -> u128 {
+    if counter < GEN0 {
+        start_price
+    } else {
+        start_price + E24 * (1 + (counter - GEN0) / GEN_NEXT) as u128
+    }
+}
+
+fn compute_price(counter: u32, num: u32, start_price: u128) -> u128 {
     let mut num = num;
     let mut cost: u128 = 0;
     let mut gen_diff;
@@ -485,6 +1199,8 @@ fn compute_price(counter: u32, num: u32, start_price: u128) -> u128 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{test_utils::get_logs, testing_env};
 
     fn compute_price_h(counter: u32, num: u32, start_price: u128) -> u128 {
         compute_price(counter, num, start_price * E24) / E24
@@ -534,4 +1250,478 @@ mod tests {
         assert_eq!(compute_price_h(554, 10, 10), 10 + 9 * 11);
         assert_eq!(compute_price_h(555, 10, 10), 10 * 11);
     }
+
+    #[test]
+    fn test_compute_unit_price() {
+        let unit_price_h = |counter| compute_unit_price(counter, 10 * E24) / E24;
+        assert_eq!(unit_price_h(0), 10);
+        assert_eq!(unit_price_h(554), 10);
+        assert_eq!(unit_price_h(555), 11);
+        assert_eq!(unit_price_h(654), 11);
+        assert_eq!(unit_price_h(655), 12);
+        for counter in [0, 554, 555, 654, 655, 1000] {
+            assert_eq!(
+                compute_unit_price(counter, 10 * E24),
+                compute_price(counter, 1, 10 * E24)
+            );
+        }
+    }
+
+    #[test]
+    fn price_schedule() {
+        let mut contract = new_contract();
+        contract.counter = 553;
+        assert_eq!(
+            contract.price_schedule(4),
+            [PRICE, PRICE, PRICE + E24, PRICE + E24].map(U128).to_vec()
+        );
+        contract.counter = 653;
+        let schedule = contract.price_schedule(4);
+        assert_eq!(
+            schedule,
+            [PRICE + E24, PRICE + E24, PRICE + 2 * E24, PRICE + 2 * E24]
+                .map(U128)
+                .to_vec()
+        );
+        let total: u128 = schedule.iter().map(|price| price.0).sum();
+        assert_eq!(total, contract.price(4));
+        assert_eq!(contract.price_schedule(SIZE + 1).len(), SIZE as usize);
+    }
+
+    #[test]
+    fn counter_near_max() {
+        let mut contract = new_contract();
+        contract.counter = u32::MAX - 1;
+        // as if the ids were burned and drawn again that many times
+        contract.burns_reopened = u32::MAX - 1;
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        assert_eq!(contract.counter, u32::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minted tokens counter overflow")]
+    fn counter_overflow() {
+        let mut contract = new_contract();
+        contract.counter = u32::MAX - 1;
+        contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+    }
+
+    /// Mint one token for alice with `deposit` attached, returning the storage cost
+    fn mint_for_alice(contract: &mut Contract, with_cheddar: bool, deposit: Balance) -> Balance {
+        set_context(&alice(), deposit);
+        let storage_usage = env::storage_usage();
+        contract.nft_mint_one(with_cheddar);
+        (env::storage_usage() - storage_usage) as Balance * env::storage_byte_cost()
+    }
+
+    #[test]
+    fn no_royalties_near_refunded() {
+        let mut contract = new_contract();
+        let deposit = PRICE + ONE_NEAR;
+        let storage_cost = mint_for_alice(&mut contract, false, deposit);
+        assert_eq!(transfers(), vec![(alice(), deposit - storage_cost)]);
+    }
+
+    #[test]
+    fn no_royalties_cheddar_untouched() {
+        let mut contract = new_contract();
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        let storage_cost = mint_for_alice(&mut contract, true, ONE_NEAR);
+        assert_eq!(contract.balance_of(&alice()).0, 100 * ONE_NEAR);
+        assert_eq!(transfers(), vec![(alice(), ONE_NEAR - storage_cost)]);
+    }
+
+    #[test]
+    fn cheddar_spent_event() {
+        let mut contract = new_contract();
+        contract.sale.initial_royalties = Some(Royalties::default());
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        mint_for_alice(&mut contract, true, ONE_NEAR);
+        // 1 NEAR = 10 cheddar with a 10% discount
+        let cost = 90 * ONE_NEAR;
+        assert_eq!(contract.balance_of(&alice()).0, 100 * ONE_NEAR - cost);
+        assert!(get_logs().contains(&format!(
+            r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"cheddar_spent","data":[{{"account_id":"alice.near","num":1,"amount":"{}"}}]}}"#,
+            cost
+        )));
+    }
+
+    #[test]
+    fn revenue() {
+        let mut contract = new_contract();
+        contract.sale.initial_royalties = Some(Royalties::default());
+        let cost = contract.minting_cost(&alice(), 2).0;
+        set_context(&alice(), cost + ONE_NEAR);
+        contract.nft_mint_many(false, 2, None, None);
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        mint_for_alice(&mut contract, true, ONE_NEAR);
+        let revenue = contract.get_revenue();
+        assert_eq!(revenue.total_near_raised.0, cost);
+        assert_eq!(revenue.total_cheddar_raised.0, 90 * ONE_NEAR);
+    }
+
+    #[test]
+    fn no_revenue_without_royalties() {
+        let mut contract = new_contract();
+        mint_for_alice(&mut contract, false, PRICE + ONE_NEAR);
+        assert_eq!(contract.get_revenue().total_near_raised.0, 0);
+    }
+
+    #[test]
+    fn no_cheddar_spent_event_without_royalties() {
+        let mut contract = new_contract();
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        mint_for_alice(&mut contract, true, ONE_NEAR);
+        assert!(get_logs().iter().all(|log| !log.contains("cheddar_spent")));
+    }
+
+    /// Mint one token from alice to bob with `deposit` attached, returning the storage cost
+    fn gift_to_bob(
+        contract: &mut Contract,
+        refund_to: Option<AccountId>,
+        deposit: Balance,
+    ) -> Balance {
+        set_context(&alice(), deposit);
+        let storage_usage = env::storage_usage();
+        contract.nft_mint_to(bob(), false, 1, refund_to, None);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 1);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 0);
+        (env::storage_usage() - storage_usage) as Balance * env::storage_byte_cost()
+    }
+
+    #[test]
+    fn mint_to_refunds_payer() {
+        let mut contract = new_contract();
+        let deposit = PRICE + ONE_NEAR;
+        let storage_cost = gift_to_bob(&mut contract, None, deposit);
+        assert_eq!(transfers(), vec![(alice(), deposit - storage_cost)]);
+    }
+
+    #[test]
+    fn mint_to_refunds_given_account() {
+        let mut contract = new_contract();
+        let deposit = PRICE + ONE_NEAR;
+        let storage_cost = gift_to_bob(&mut contract, Some(owner()), deposit);
+        assert_eq!(transfers(), vec![(owner(), deposit - storage_cost)]);
+    }
+
+    #[test]
+    fn mint_to_implicit_account() {
+        let mut contract = new_contract();
+        let implicit: AccountId = "0f".repeat(32).parse().unwrap();
+        let deposit = PRICE + ONE_NEAR;
+        set_context(&alice(), deposit);
+        let storage_usage = env::storage_usage();
+        contract.nft_mint_to(implicit.clone(), false, 1, None, None);
+        let storage_cost =
+            (env::storage_usage() - storage_usage) as Balance * env::storage_byte_cost();
+        assert_eq!(contract.nft_supply_for_owner(implicit).0, 1);
+        assert_eq!(transfers(), vec![(alice(), deposit - storage_cost)]);
+    }
+
+    #[test]
+    fn mint_to_malformed_receiver() {
+        // `receiver_id` is validated while the call arguments are deserialized, before minting
+        let too_long = "a".repeat(65);
+        for receiver_id in &["Bob.near", "bob..near", "bob.near ", &too_long] {
+            let json = format!("\"{}\"", receiver_id);
+            let err = near_sdk::serde_json::from_str::<AccountId>(&json).unwrap_err();
+            assert!(err.to_string().contains("the account ID is invalid"));
+        }
+    }
+
+    #[test]
+    fn partial_fulfillment() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(SIZE - 2, &bob(), true, false);
+        contract.sale.initial_royalties = Some(Royalties::default());
+        let deposit = 3 * PRICE + ONE_NEAR;
+        set_context(&alice(), deposit);
+        let storage_usage = env::storage_usage();
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), false, false);
+        let storage_cost =
+            (env::storage_usage() - storage_usage) as Balance * env::storage_byte_cost();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(contract.nft_total_minted(), SIZE);
+        assert!(transfers().contains(&(owner(), 2 * PRICE)));
+        assert!(transfers().contains(&(alice(), deposit - storage_cost - 2 * PRICE)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot mint more than 50 per transaction")]
+    fn max_mint_batch() {
+        let mut contract = new_contract();
+        set_context(&alice(), PRICE);
+        contract.nft_mint_many(false, u32::MAX, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is frozen")]
+    fn frozen_mint() {
+        let mut contract = new_contract();
+        contract.admin_freeze_all();
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    fn frozen_admin_mint() {
+        let mut contract = new_contract();
+        contract.add_admin(alice(), None);
+        contract.admin_freeze_all();
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit is more than 3x the cost")]
+    fn max_overpay() {
+        let mut contract = new_contract();
+        contract.admin_set_max_overpay_multiplier(Some(3));
+        set_context(&alice(), 10 * PRICE);
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    fn max_overpay_off() {
+        let mut contract = new_contract();
+        set_context(&alice(), 10 * PRICE);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+
+        // within the limit is refunded as usual
+        set_context(&owner(), 1);
+        contract.admin_set_max_overpay_multiplier(Some(3));
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+    }
+
+    #[test]
+    fn mint_many_detailed() {
+        let mut contract = new_contract();
+        contract.sale.initial_royalties = Some(Royalties::default());
+        let deposit = 2 * PRICE + ONE_NEAR;
+        set_context(&alice(), deposit);
+        let result = contract.nft_mint_many_detailed(false, 2);
+        assert_eq!(result.tokens.len(), 2);
+        assert_eq!(result.charged.0, 2 * PRICE);
+        assert!(result.storage_cost.0 > 0);
+        assert_eq!(
+            result.charged.0 + result.refunded.0 + result.storage_cost.0,
+            deposit
+        );
+        assert!(transfers().contains(&(alice(), result.refunded.0)));
+    }
+
+    #[test]
+    fn mint_request_storage() {
+        let mut contract = new_contract();
+        let deposit = PRICE + ONE_NEAR;
+        set_context(&alice(), deposit);
+        let storage_usage = env::storage_usage();
+        contract.nft_mint_many(false, 1, None, Some("mint-1".to_string()));
+        // the request is in the storage paid by the minter
+        let storage_cost =
+            (env::storage_usage() - storage_usage) as Balance * env::storage_byte_cost();
+        assert!(contract.mint_requests.contains_key(&alice()));
+        assert_eq!(transfers(), vec![(alice(), deposit - storage_cost)]);
+
+        // a mint without request_id keeps the request until it expires
+        set_context(&alice(), deposit);
+        contract.nft_mint_many(false, 1, None, None);
+        assert!(contract.mint_requests.contains_key(&alice()));
+        testing_env!(context(&alice())
+            .block_timestamp((NOW + MINT_REQUEST_WINDOW_MS) * 1_000_000)
+            .attached_deposit(deposit)
+            .build());
+        contract.nft_mint_many(false, 1, None, None);
+        assert!(!contract.mint_requests.contains_key(&alice()));
+    }
+
+    #[test]
+    fn mint_request_id() {
+        let mut contract = new_contract();
+        let request_id = Some("mint-1".to_string());
+        set_context(&alice(), 2 * PRICE + ONE_NEAR);
+        let tokens = contract.nft_mint_many(false, 2, None, request_id.clone());
+        set_context(&alice(), 2 * PRICE + ONE_NEAR);
+        let repeated = contract.nft_mint_many(false, 2, None, request_id.clone());
+        assert_eq!(
+            repeated.iter().map(|t| &t.token_id).collect::<Vec<_>>(),
+            tokens.iter().map(|t| &t.token_id).collect::<Vec<_>>()
+        );
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+        assert_eq!(transfers(), vec![(alice(), 2 * PRICE + ONE_NEAR)]);
+
+        // a new request or the same one after the window mints again
+        set_context(&alice(), 2 * PRICE + ONE_NEAR);
+        contract.nft_mint_many(false, 1, None, Some("mint-2".to_string()));
+        testing_env!(context(&alice())
+            .block_timestamp((NOW + MINT_REQUEST_WINDOW_MS) * 1_000_000)
+            .attached_deposit(2 * PRICE + ONE_NEAR)
+            .build());
+        contract.nft_mint_many(false, 1, None, Some("mint-2".to_string()));
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 4);
+    }
+
+    /// Contract paying the price to the owner with a 10% referral bonus
+    fn referral_contract() -> Contract {
+        let mut contract = new_contract();
+        contract.admin_set_referral_bps(Some(1_000));
+        contract.sale.initial_royalties = Some(Royalties::default());
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract
+    }
+
+    #[test]
+    fn referral() {
+        let mut contract = referral_contract();
+        contract.nft_mint_many(false, 1, Some(bob()), None);
+        assert!(transfers().contains(&(bob(), PRICE / 10)));
+        assert!(transfers().contains(&(owner(), PRICE - PRICE / 10)));
+        assert_eq!(contract.referral_earnings(&bob()).0, PRICE / 10);
+    }
+
+    #[test]
+    fn no_referrer() {
+        let mut contract = referral_contract();
+        contract.nft_mint_many(false, 1, None, None);
+        assert!(transfers().contains(&(owner(), PRICE)));
+        assert_eq!(contract.referral_earnings(&bob()).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't refer yourself")]
+    fn self_referral() {
+        let mut contract = referral_contract();
+        contract.nft_mint_many(false, 1, Some(alice()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit does not cover storage, missing")]
+    fn deposit_below_storage_cost() {
+        let mut contract = new_contract();
+        mint_for_alice(&mut contract, false, 0);
+    }
+
+    #[test]
+    fn minter_account() {
+        let proxy: AccountId = "proxy.near".parse().unwrap();
+        let mut contract = new_contract();
+        let deposit = PRICE + ONE_NEAR;
+        testing_env!(context(&proxy)
+            .signer_account_id(alice())
+            .attached_deposit(deposit)
+            .build());
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+        assert_eq!(transfers()[0].0, alice());
+
+        contract.sale.use_predecessor = true;
+        testing_env!(context(&proxy)
+            .signer_account_id(alice())
+            .attached_deposit(deposit)
+            .build());
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+        assert_eq!(contract.nft_supply_for_owner(proxy.clone()).0, 1);
+        assert_eq!(transfers()[0].0, proxy);
+    }
+
+    #[test]
+    fn accepts_cheddar() {
+        let mut contract = new_contract();
+        assert!(contract.accepts_cheddar());
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        mint_for_alice(&mut contract, true, ONE_NEAR);
+
+        set_context(&owner(), 1);
+        contract.admin_set_accepts_cheddar(false);
+        assert!(!contract.accepts_cheddar());
+        mint_for_alice(&mut contract, false, PRICE + ONE_NEAR);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Paying with cheddar is not accepted")]
+    fn cheddar_not_accepted() {
+        let mut contract = new_contract();
+        contract.admin_set_accepts_cheddar(false);
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(100 * ONE_NEAR));
+        mint_for_alice(&mut contract, true, ONE_NEAR);
+    }
+
+    fn new_with_cheddar(cheddar_near: u32, cheddar_discount: u32) -> Contract {
+        set_context(&owner(), 1);
+        Contract::new_with_sale_price(
+            owner(),
+            InitialMetadata::default(),
+            SIZE,
+            U128(PRICE),
+            cheddar(),
+            cheddar_near,
+            cheddar_discount,
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "collection size must be positive")]
+    fn new_zero_size() {
+        set_context(&owner(), 1);
+        Contract::new_with_sale_price(
+            owner(),
+            InitialMetadata::default(),
+            0,
+            U128(PRICE),
+            cheddar(),
+            10_000,
+            10,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cheddar_near must be positive")]
+    fn new_zero_cheddar_near() {
+        new_with_cheddar(0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "1 cheddar is rather worth less than 10NEAR")]
+    fn new_low_cheddar_near() {
+        new_with_cheddar(100, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "cheddar discount can't be more than 100%")]
+    fn new_full_cheddar_discount() {
+        new_with_cheddar(10_000, 100);
+    }
+
+    #[test]
+    fn set_cheddar_discount() {
+        let mut contract = new_with_cheddar(10_000, 10);
+        contract.admin_set_cheddar_discount(25);
+        assert_eq!(contract.cheddar_boost, 75);
+    }
+
+    #[test]
+    #[should_panic(expected = "cheddar discount can't be more than 100%")]
+    fn set_full_cheddar_discount() {
+        let mut contract = new_with_cheddar(10_000, 10);
+        contract.admin_set_cheddar_discount(100);
+    }
 }
@@ -1,4 +1,3 @@
-/*
 use crate::*;
 
 use near_sdk::{
@@ -10,6 +9,7 @@ use near_units::parse_near;
 /// 0.064311394105062020653824 N
 pub(crate) const ACCESS_KEY_ALLOWANCE: u128 = parse_near!("0 N");
 
+/// Default deposit sent along with a claimed linkdrop, see `admin_set_linkdrop`
 pub(crate) const LINKDROP_DEPOSIT: u128 = parse_near!("0.02 N");
 /// can take 0.5 of access key since gas required is 6.6 times what was actually used
 const ON_CREATE_ACCOUNT_GAS: Gas = Gas(30_000_000_000_000);
@@ -18,9 +18,18 @@ const NO_DEPOSIT: Balance = 0;
 /// Gas attached to the callback from account creation.
 pub const ON_CREATE_ACCOUNT_CALLBACK_GAS: Gas = Gas(10_000_000_000_000);
 
-*/
+/// Default account creation contract of the network, see `admin_set_linkdrop`
+pub(crate) fn default_linkdrop_contract() -> AccountId {
+    AccountId::new_unchecked(
+        (if cfg!(feature = "testnet") {
+            "testnet"
+        } else {
+            "near"
+        })
+        .to_string(),
+    )
+}
 
-/*
 // NOTE
 // all linkdrops will use only NEAR as payment, NOT CHEDDAR!
 //
@@ -40,9 +49,15 @@ impl Contract {
         let account = &env::predecessor_account_id();
         self.assert_can_mint(account, 1);
         let total_cost = self.cost_of_linkdrop(account).0;
+        require!(
+            deposit >= total_cost,
+            "Not enough deposit to create a linkdrop"
+        );
         self.pending_tokens += 1;
+        self.assert_supply_invariant();
         let mint_for_free = self.is_owner(account);
         self.use_whitelist_allowance(account, 1);
+        self.use_free_mints(account, 1);
         log!("Total cost of creation is {}", total_cost);
         refund(account, deposit - total_cost);
         self.send(public_key, mint_for_free)
@@ -54,14 +69,12 @@ impl Contract {
     }
     /// Allows given public key to claim sent balance.
     /// Takes ACCESS_KEY_ALLOWANCE as fee from deposit to cover account creation via an access key.
-
     /// Claim tokens for specific account that are attached to the public key this tx is signed with.
     #[private]
     pub fn claim(&mut self, account_id: AccountId) -> Promise {
-        // require!(false, "Cannot claim at this time try again later");
         let (mint_for_free, deletion_promise) = self.delete_current_access_key();
         deletion_promise
-            .then(Promise::new(account_id.clone()).transfer(LINKDROP_DEPOSIT))
+            .then(Promise::new(account_id.clone()).transfer(self.linkdrop_deposit))
             .then(ext_self::link_callback(
                 account_id.clone(),
                 mint_for_free,
@@ -84,7 +97,6 @@ impl Contract {
         new_account_id: AccountId,
         new_public_key: PublicKey,
     ) -> Promise {
-        // require!(false, "Cannot claim at this time try again later");
         let (mint_for_free, deletion_promise) = self.delete_current_access_key();
         deletion_promise
             .and(self.create_account(new_account_id.clone(), new_public_key))
@@ -104,9 +116,8 @@ impl Contract {
     }
 
     /// Returns the balance associated with given key.
-    #[allow(unused_variables)]
     pub fn get_key_balance(&self) -> U128 {
-        LINKDROP_DEPOSIT.into()
+        self.linkdrop_deposit.into()
     }
 
     pub fn check_key(&self, public_key: PublicKey) -> bool {
@@ -120,15 +131,9 @@ impl Contract {
         }
     }
 
+    /// Contract creating the accounts of claimed linkdrops
     pub fn get_linkdrop_contract(&self) -> AccountId {
-        AccountId::new_unchecked(
-            (if cfg!(feature = "testnet") {
-                "testnet"
-            } else {
-                "near"
-            })
-            .to_string(),
-        )
+        self.linkdrop_contract.clone()
     }
 }
 
@@ -143,7 +148,7 @@ impl Contract {
             new_account_id,
             new_public_key,
             self.get_linkdrop_contract(),
-            LINKDROP_DEPOSIT,
+            self.linkdrop_deposit,
             ON_CREATE_ACCOUNT_GAS,
         )
     }
@@ -171,4 +176,111 @@ impl Contract {
         )
     }
 }
-*/
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{
+        test_utils::VMContextBuilder, testing_env, PromiseResult, RuntimeFeesConfig, VMConfig,
+    };
+
+    fn public_key() -> PublicKey {
+        "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp"
+            .parse()
+            .unwrap()
+    }
+
+    /// Context of the callback, with the result of the promise it was waiting for
+    fn set_callback_context(builder: &mut VMContextBuilder, result: PromiseResult) {
+        testing_env!(
+            builder.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    #[test]
+    fn create_linkdrop() {
+        let mut contract = new_contract();
+        let cost = contract.cost_of_linkdrop(&alice()).0;
+        assert_eq!(
+            cost,
+            LINKDROP_DEPOSIT + parse_near!("8 mN") + PRICE + contract.token_storage_cost().0
+        );
+        set_context(&alice(), cost);
+        contract.create_linkdrop(public_key());
+        assert!(contract.check_key(public_key()));
+        assert_eq!(contract.pending_tokens, 1);
+        assert_eq!(contract.tokens_left(), SIZE - 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough deposit to create a linkdrop")]
+    fn create_linkdrop_not_enough_deposit() {
+        let mut contract = new_contract();
+        set_context(&alice(), PRICE);
+        contract.create_linkdrop(public_key());
+    }
+
+    #[test]
+    fn failed_linkdrop_releases_token() {
+        let mut contract = new_contract();
+        let cost = contract.cost_of_linkdrop(&alice()).0;
+        set_context(&alice(), cost);
+        contract.create_linkdrop(public_key());
+
+        let mut builder = context(&tenk());
+        builder.signer_account_id(alice()).attached_deposit(cost);
+        set_callback_context(&mut builder, PromiseResult::Successful(vec![]));
+        contract.on_send_with_callback();
+        assert_eq!(contract.tokens_left(), SIZE - 1);
+
+        set_callback_context(&mut builder, PromiseResult::Failed);
+        contract.on_send_with_callback();
+        assert_eq!(contract.pending_tokens, 0);
+        assert_eq!(contract.tokens_left(), SIZE);
+    }
+
+    #[test]
+    fn reclaim_pending() {
+        let mut contract = new_contract();
+        set_context(&alice(), contract.cost_of_linkdrop(&alice()).0);
+        contract.create_linkdrop(public_key());
+        assert_eq!(contract.tokens_left(), SIZE - 1);
+
+        set_context(&owner(), 1);
+        contract.admin_reclaim_pending(public_key());
+        assert!(!contract.check_key(public_key()));
+        assert_eq!(contract.tokens_left(), SIZE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Linkdrop key not found or already claimed")]
+    fn reclaim_unknown_key() {
+        let mut contract = new_contract();
+        contract.admin_reclaim_pending(public_key());
+    }
+
+    #[test]
+    fn linkdrop_config() {
+        let mut contract = new_contract();
+        assert_eq!(
+            contract.get_linkdrop_contract(),
+            default_linkdrop_contract()
+        );
+        assert_eq!(contract.get_key_balance().0, LINKDROP_DEPOSIT);
+
+        let proxy: AccountId = "proxy.near".parse().unwrap();
+        contract.admin_set_linkdrop(proxy.clone(), U128(ONE_NEAR));
+        assert_eq!(contract.get_linkdrop_contract(), proxy);
+        assert_eq!(contract.get_key_balance().0, ONE_NEAR);
+        assert_eq!(
+            contract.cost_of_linkdrop(&owner()).0,
+            ONE_NEAR + contract.token_storage_cost().0
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Upgrade of a contract deployed with the first version of the state layout.
+//!
+//! Deploy the new code and call `migrate` in the same batch transaction, so that the contract is
+//! never left with code that can't read its state, see `scripts/upgrade.ts`.
+use crate::*;
+use std::collections::HashMap;
+
+/// Royalties as stored before `allow_zero_owner_remainder`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldRoyalties {
+    accounts: HashMap<AccountId, u16>,
+    percent: u16,
+}
+
+impl From<OldRoyalties> for Royalties {
+    fn from(royalties: OldRoyalties) -> Self {
+        Royalties {
+            accounts: royalties.accounts,
+            percent: royalties.percent,
+            allow_zero_owner_remainder: false,
+        }
+    }
+}
+
+/// Sale as stored by the first version
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldSale {
+    royalties: Option<OldRoyalties>,
+    initial_royalties: Option<OldRoyalties>,
+    presale_start: Option<TimestampMs>,
+    public_sale_start: Option<TimestampMs>,
+    allowance: Option<u32>,
+    presale_price: Option<U128>,
+    price: U128,
+    mint_rate_limit: Option<u32>,
+}
+
+impl From<OldSale> for Sale {
+    fn from(sale: OldSale) -> Self {
+        Sale {
+            royalties: sale.royalties.map(Into::into),
+            initial_royalties: sale.initial_royalties.map(Into::into),
+            presale_start: sale.presale_start,
+            public_sale_start: sale.public_sale_start,
+            public_sale_end: None,
+            allowance: sale.allowance,
+            presale_price: sale.presale_price,
+            price: sale.price,
+            mint_rate_limit: sale.mint_rate_limit,
+            freeze_price_on_open: false,
+            holder_discount_bps: None,
+            use_predecessor: false,
+            presale_supply_cap: None,
+            referral_bps: None,
+            mint_cooldown_ms: None,
+            max_overpay_multiplier: None,
+        }
+    }
+}
+
+/// Contract state as stored by the first version
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct OldContract {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+    raffle: Raffle,
+    pending_tokens: u32,
+    cheddar: AccountId,
+    cheddar_deposits: LookupMap<AccountId, u128>,
+    cheddar_near: u128,
+    cheddar_boost: u32,
+    accounts: LookupMap<PublicKey, bool>,
+    whitelist: LookupMap<AccountId, u32>,
+    sale: OldSale,
+    admins: UnorderedSet<AccountId>,
+    counter: u32,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Read the state of the first version and set the fields added since to their defaults.
+    ///
+    /// - The old whitelist is still read, but its entries are only listed by `whitelist_entries`
+    ///   once they change.
+    /// - Holders weren't counted, `num_holders` is unknown until `admin_count_holders` is done.
+    /// - Ids moved in the old raffle don't know their index, call `admin_index_raffle` until it
+    ///   returns 0 before reserving specific ids.
+    /// - Past draws weren't recorded: `final_provenance` only lists the draws made after the
+    ///   upgrade, and `sold_out_at` stays unset for a collection which sold out before it.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate() -> Self {
+        let old: OldContract = env::state_read().expect("No contract state to migrate");
+        // the first version couldn't burn, every id is either minted or in the raffle
+        let initial_size = old.counter + old.raffle.len() as u32;
+        Self {
+            tokens: old.tokens,
+            metadata: old.metadata,
+            raffle: old.raffle,
+            pending_tokens: old.pending_tokens,
+            cheddar: old.cheddar,
+            cheddar_deposits: Deposits::new(old.cheddar_deposits),
+            cheddar_near: old.cheddar_near,
+            cheddar_boost: old.cheddar_boost,
+            accounts: old.accounts,
+            whitelist: Whitelist::new(old.whitelist),
+            sale: old.sale.into(),
+            admins: old.admins,
+            counter: old.counter,
+            media_extension: "png".to_string(),
+            reference_extension: "json".to_string(),
+            metadata_frozen: false,
+            token_hashes: LookupMap::new(StorageKey::TokenHashes),
+            reopen_on_burn: false,
+            revealed: false,
+            placeholder_media: None,
+            placeholder_reference: None,
+            events_enabled: true,
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            draw_order: Vector::new(StorageKey::DrawOrder),
+            raffle_seed: None,
+            linkdrop_contract: default_linkdrop_contract(),
+            linkdrop_deposit: LINKDROP_DEPOSIT,
+            token_extras: LookupMap::new(StorageKey::TokenExtras),
+            sold_out_at: None,
+            accepts_cheddar: true,
+            transfers_locked_until: None,
+            free_mint_list: LookupMap::new(StorageKey::FreeMintList),
+            soulbound: LookupMap::new(StorageKey::Soulbound),
+            presale_minted: 0,
+            referral_earnings: LookupMap::new(StorageKey::ReferralEarnings),
+            allowlist_root: None,
+            last_mint_at: LookupMap::new(StorageKey::LastMintAt),
+            token_royalties: LookupMap::new(StorageKey::TokenRoyalties),
+            num_holders: 0,
+            token_minted_at: LookupMap::new(StorageKey::TokenMintedAt),
+            copies: None,
+            royalties_frozen: false,
+            frozen: false,
+            total_near_raised: 0,
+            total_cheddar_raised: 0,
+            mint_requests: LookupMap::new(StorageKey::MintRequests),
+            on_chain_media: false,
+            token_media: LookupMap::new(StorageKey::TokenMedia),
+            auto_approve_account: None,
+            admin_permissions: LookupMap::new(StorageKey::AdminPermissions),
+            storage_deposits_total: 0,
+            initial_size,
+            burns_reopened: 0,
+            raffle_index_from: if old.counter > 0 { Some(0) } else { None },
+            // going over every token doesn't fit in one call for a large collection
+            holders_count: if old.counter > 0 {
+                Some(HoldersCount::Counting(None))
+            } else {
+                None
+            },
+            counted_holders: UnorderedSet::new(StorageKey::CountedHolders),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::IntoStorageKey;
+
+    /// Store `contract` with the layout of the first version, with alice whitelisted for 3 tokens
+    fn write_old_state(contract: Contract) {
+        // the first version didn't store the index of moved values
+        for value in 0..SIZE as u64 {
+            env::storage_remove(
+                &[
+                    &StorageKey::Raffle.into_storage_key()[..],
+                    b"i",
+                    &value.to_le_bytes(),
+                ]
+                .concat(),
+            );
+        }
+        let mut whitelist = LookupMap::new(StorageKey::Whitelist);
+        whitelist.insert(&alice(), &3);
+        let old = OldContract {
+            tokens: contract.tokens,
+            metadata: contract.metadata,
+            raffle: contract.raffle,
+            pending_tokens: contract.pending_tokens,
+            cheddar: contract.cheddar,
+            cheddar_deposits: contract.cheddar_deposits.balances,
+            cheddar_near: contract.cheddar_near,
+            cheddar_boost: contract.cheddar_boost,
+            accounts: contract.accounts,
+            whitelist,
+            sale: OldSale {
+                royalties: Some(OldRoyalties {
+                    accounts: HashMap::from([(bob(), 10_000)]),
+                    percent: 500,
+                }),
+                initial_royalties: None,
+                presale_start: contract.sale.presale_start,
+                public_sale_start: contract.sale.public_sale_start,
+                allowance: contract.sale.allowance,
+                presale_price: contract.sale.presale_price,
+                price: contract.sale.price,
+                mint_rate_limit: contract.sale.mint_rate_limit,
+            },
+            admins: contract.admins,
+            counter: contract.counter,
+        };
+        env::state_write(&old);
+    }
+
+    #[test]
+    fn migrate() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        write_old_state(contract);
+
+        set_context(&tenk(), 0);
+        let contract = Contract::migrate();
+        assert_eq!(contract.nft_total_supply().0, 2);
+        assert_eq!(contract.nft_total_minted(), 2);
+        assert_eq!(contract.tokens_left(), SIZE - 2);
+        assert_eq!(
+            contract
+                .nft_token(tokens[0].token_id.clone())
+                .unwrap()
+                .owner_id,
+            alice()
+        );
+        assert_eq!(contract.sale.price.0, PRICE);
+        let royalties = contract.sale.royalties.as_ref().unwrap();
+        assert_eq!(royalties.percent, 500);
+        assert!(!royalties.allow_zero_owner_remainder);
+        assert!(contract.accepts_cheddar());
+        assert!(contract.sale.public_sale_end.is_none());
+        assert_eq!(contract.num_holders(), None);
+        assert_eq!(contract.raffle_stats().initial_size, SIZE);
+    }
+
+    #[test]
+    fn migrate_holders() {
+        let mut contract = new_contract();
+        let alice_tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        let bob_token = contract.nft_mint_many_ungaurded(1, &bob(), true, false)[0]
+            .token_id
+            .clone();
+        write_old_state(contract);
+        set_context(&tenk(), 0);
+        let mut contract = Contract::migrate();
+
+        set_context(&owner(), 1);
+        assert!(!contract.admin_count_holders(Some(1)));
+        assert_eq!(contract.num_holders(), None);
+
+        // whether or not bob was counted already, owner is counted once it holds a token
+        set_context(&bob(), 1);
+        contract.nft_transfer(owner(), bob_token, None, None);
+
+        set_context(&owner(), 1);
+        while !contract.admin_count_holders(Some(1)) {}
+        assert_eq!(contract.num_holders(), Some(2));
+        assert!(contract.counted_holders.is_empty());
+
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), alice_tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.num_holders(), Some(3));
+    }
+
+    #[test]
+    fn migrate_whitelist() {
+        write_old_state(new_contract());
+        set_context(&tenk(), 0);
+        let mut contract = Contract::migrate();
+        assert!(contract.whitelisted(&alice()));
+        assert_eq!(contract.remaining_allowance(&alice()), Some(3));
+        assert!(!contract.whitelisted(&bob()));
+        assert!(contract.whitelist_entries(0, 10).is_empty());
+
+        contract.sale.public_sale_start = None;
+        contract.sale.presale_start = Some(0);
+        contract.use_whitelist_allowance(&alice(), 1);
+        assert_eq!(contract.remaining_allowance(&alice()), Some(2));
+        assert_eq!(contract.whitelist_entries(0, 10), vec![(alice(), 2)]);
+
+        set_context(&owner(), 1);
+        contract.admin_remove_whitelist(vec![alice()]);
+        assert!(!contract.whitelisted(&alice()));
+    }
+
+    #[test]
+    fn migrate_raffle() {
+        let mut contract = new_contract();
+        let drawn = contract.nft_mint_many_ungaurded(5, &alice(), true, false);
+        write_old_state(contract);
+        set_context(&tenk(), 0);
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.sold_out_at(), None);
+
+        set_context(&owner(), 1);
+        assert_eq!(contract.admin_index_raffle(Some(5)), SIZE as u64 - 10);
+        assert_eq!(contract.admin_index_raffle(None), 0);
+        assert_eq!(contract.admin_index_raffle(None), 0);
+
+        let left = contract.remaining_sample(SIZE);
+        assert_eq!(left.len() as u32, SIZE - 5);
+        contract.admin_mint_specific(left.clone(), bob());
+        assert_eq!(contract.tokens_left(), 0);
+        assert!(contract.sold_out_at().is_some());
+        // draws before the upgrade weren't recorded
+        let provenance: Vec<u64> = contract
+            .final_provenance()
+            .into_iter()
+            .map(u64::from)
+            .collect();
+        assert_eq!(provenance, left);
+        assert!(drawn
+            .iter()
+            .all(|token| !provenance.contains(&token.token_id.parse().unwrap())));
+    }
+
+    #[test]
+    #[should_panic(expected = "The raffle isn't indexed yet, call admin_index_raffle")]
+    fn migrate_raffle_not_indexed() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(5, &alice(), true, false);
+        write_old_state(contract);
+        set_context(&tenk(), 0);
+        let mut contract = Contract::migrate();
+        set_context(&owner(), 1);
+        contract.admin_mint_specific(vec![SIZE as u64 - 1], bob());
+    }
+}
@@ -1,11 +1,16 @@
+use crate::user::ext_ft;
 use crate::*;
+use near_sdk::assert_one_yocto;
+use std::convert::TryInto;
 
 #[near_bindgen]
 impl Contract {
     // Owner private methods
 
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn transfer_ownership(&mut self, new_owner: AccountId) -> bool {
+        assert_one_yocto();
         self.assert_owner();
         env::log_str(&format!(
             "{} transfers ownership to {}",
@@ -16,31 +21,63 @@ impl Contract {
     }
 
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_initial_royalties(&mut self, initial_royalties: Royalties) -> bool {
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        require!(!self.royalties_frozen, "Royalties are frozen");
         initial_royalties.validate();
         self.sale.initial_royalties = Some(initial_royalties);
         true
     }
 
+    /// Replace the split of the primary sale, e.g. to add a payee during the drop.
+    /// The shares of the accounts must add up to 10000 basis points.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_royalties(&mut self, royalties: Royalties) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(!self.royalties_frozen, "Royalties are frozen");
+        royalties.validate();
+        self.sale.initial_royalties = Some(royalties);
+        true
+    }
+
+    /// Make the primary sale royalties immutable. Can't be undone.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_freeze_royalties(&mut self) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.royalties_frozen = true;
+        true
+    }
+
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_royalties(&mut self, royalties: Royalties) -> bool {
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
         royalties.validate();
         self.sale.royalties = Some(royalties);
         true
     }
 
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_allowance(&mut self, allowance: u32) -> bool {
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
         self.sale.allowance = Some(allowance);
         true
     }
 
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_uri(&mut self, uri: String) -> bool {
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_TOKENS);
         let mut metadata = self.metadata.get().unwrap();
         log!("New URI: {}", &uri);
         metadata.base_uri = Some(uri);
@@ -48,10 +85,491 @@ impl Contract {
         true
     }
 
+    /// Set the extensions of the token media and reference files, e.g. `png` and `json`.
+    /// Only possible before the first mint and while metadata isn't frozen.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_metadata_extensions(
+        &mut self,
+        media_extension: String,
+        reference_extension: String,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(!self.metadata_frozen, "Metadata is frozen");
+        require!(self.counter == 0, "Tokens were already minted");
+        self.media_extension = media_extension;
+        self.reference_extension = reference_extension;
+        true
+    }
+
+    /// Set the `copies` advertised in the metadata of the tokens, e.g. the collection size for
+    /// an editions drop. Only possible before the first mint and while metadata isn't frozen.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_copies(&mut self, copies: Option<u64>) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(!self.metadata_frozen, "Metadata is frozen");
+        require!(self.counter == 0, "Tokens were already minted");
+        self.copies = copies;
+        true
+    }
+
+    /// Set or clear when a token expires, in milliseconds since the epoch
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_token_expiry(
+        &mut self,
+        token_id: TokenId,
+        expires_at: Option<TimestampMs>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        let token_metadata_by_id = self.tokens.token_metadata_by_id.as_mut().unwrap();
+        let mut metadata = token_metadata_by_id
+            .get(&token_id)
+            .expect("No such token_id");
+        metadata.expires_at = expires_at.map(|expires_at| expires_at.to_string());
+        token_metadata_by_id.insert(&token_id, &metadata);
+        true
+    }
+
+    /// Set royalties of a token overriding the collection's, or None to use the collection's again
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_token_royalties(
+        &mut self,
+        token_id: TokenId,
+        royalties: Option<Royalties>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        match royalties {
+            Some(royalties) => {
+                royalties.validate();
+                self.token_royalties.insert(&token_id, &royalties);
+            }
+            None => {
+                self.token_royalties.remove(&token_id);
+            }
+        }
+        true
+    }
+
+    /// Make a token non-transferable, or transferable again. It can still be burned.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_soulbound(&mut self, token_id: TokenId, soulbound: bool) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        if soulbound {
+            require!(
+                self.tokens.owner_by_id.contains_key(&token_id),
+                "No such token_id"
+            );
+            self.soulbound.insert(&token_id, &true);
+        } else {
+            self.soulbound.remove(&token_id);
+        }
+        true
+    }
+
+    /// Set the contract creating the accounts of claimed linkdrops, e.g. a proxy contract,
+    /// and the NEAR deposit sent along with each claim.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_linkdrop(&mut self, contract_id: AccountId, deposit: U128) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.linkdrop_contract = contract_id;
+        self.linkdrop_deposit = deposit.0;
+        true
+    }
+
+    /// Release the token reserved by an unclaimed linkdrop and delete its access key
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_reclaim_pending(&mut self, public_key: PublicKey) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.accounts.remove(&public_key).is_some(),
+            "Linkdrop key not found or already claimed"
+        );
+        require!(self.pending_tokens > 0, "No pending tokens");
+        self.pending_tokens -= 1;
+        Promise::new(env::current_account_id()).delete_key(public_key);
+        true
+    }
+
+    /// Send `amount` of NEAR held by the contract to `to`, e.g. NEAR transferred to the contract
+    /// by mistake. The balance covering the contract's storage can't be withdrawn, nor the NEAR
+    /// belonging to users: prepaid storage and the deposits and storage of pending linkdrops.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_withdraw_near(&mut self, amount: U128, to: AccountId) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        let storage_reserve = env::storage_byte_cost() * env::storage_usage() as Balance;
+        let pending_linkdrops =
+            self.pending_tokens as Balance * (self.linkdrop_deposit + self.token_storage_cost().0);
+        let free_balance = env::account_balance()
+            .saturating_sub(storage_reserve + self.storage_deposits_total + pending_linkdrops);
+        require!(
+            amount.0 <= free_balance,
+            format!(
+                "Can't withdraw more than the {} yoctoNEAR not reserved for storage and users",
+                free_balance
+            )
+        );
+        Promise::new(to).transfer(amount.0);
+        true
+    }
+
+    /// Send `amount` of the fungible token `token_id` held by the contract to `to`, e.g. tokens
+    /// sent with a plain `ft_transfer`. Cheddar is excluded, the contract's Cheddar belongs to
+    /// the depositors.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_withdraw_ft(&mut self, token_id: AccountId, amount: U128, to: AccountId) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            token_id != self.cheddar,
+            "Cheddar is held for depositors, it can't be withdrawn"
+        );
+        ext_ft::ft_transfer(
+            to,
+            amount,
+            Some("TENK stray tokens withdraw".to_string()),
+            token_id,
+            1,
+            GAS_FOR_FT_TRANSFER,
+        );
+        true
+    }
+
+    /// Send the deposits of `token_id` back to their depositors, e.g. when winding down the sale.
+    /// Refunds up to `limit` depositors, at most `MAX_REFUND_BATCH`, and returns how many are left.
+    /// Call again until none is left, a failed transfer is credited back and retried next time.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_refund_all_token_deposits(
+        &mut self,
+        token_id: AccountId,
+        limit: Option<u64>,
+    ) -> u64 {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(token_id == self.cheddar, "Only CHEDDAR deposits are held");
+        let limit = limit.map_or(MAX_REFUND_BATCH, |limit| u64::min(limit, MAX_REFUND_BATCH));
+        for account_id in self.cheddar_deposits.depositors(limit) {
+            match self.cheddar_deposits.remove(&account_id) {
+                // NEP-141 rejects transfers of 0, it would be credited back and retried forever
+                Some(0) | None => {}
+                Some(amount) => {
+                    log!("Refunding {} to {}", amount, account_id);
+                    self.send_cheddar(account_id, amount);
+                }
+            }
+        }
+        self.cheddar_deposits.num_depositors()
+    }
+
+    /// Index up to `limit` more values of a raffle migrated from the first version, at most
+    /// `MAX_RAFFLE_INDEX_BATCH`, so that specific ids can be taken out of it. Returns how many
+    /// indexes are left, call again until none is left.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_index_raffle(&mut self, limit: Option<u64>) -> u64 {
+        assert_one_yocto();
+        self.assert_owner();
+        let from = match self.raffle_index_from {
+            Some(from) => from,
+            None => return 0,
+        };
+        let limit = limit.map_or(MAX_RAFFLE_INDEX_BATCH, |limit| {
+            u64::min(limit, MAX_RAFFLE_INDEX_BATCH)
+        });
+        let next = self.raffle.index_moved_values(from, limit);
+        let left = self.raffle.len().saturating_sub(next);
+        self.raffle_index_from = if left == 0 { None } else { Some(next) };
+        left
+    }
+
+    /// Count the holders of a contract migrated from the first version, going over up to `limit`
+    /// tokens, at most `MAX_HOLDERS_COUNT_BATCH`. Once all tokens are counted, the following
+    /// calls forget the accounts counted. Returns whether it's done, call again until it is.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_count_holders(&mut self, limit: Option<u64>) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        let limit = limit.map_or(MAX_HOLDERS_COUNT_BATCH, |limit| {
+            u64::min(limit, MAX_HOLDERS_COUNT_BATCH)
+        });
+        self.holders_count = match self.holders_count.take() {
+            Some(HoldersCount::Counting(from)) => Some(self.count_holders(from, limit)),
+            Some(HoldersCount::Clearing) => self.forget_counted_holders(limit),
+            None => None,
+        };
+        self.holders_count.is_none()
+    }
+
+    /// Correct the number of tokens held for unclaimed linkdrops, if it drifted from the
+    /// linkdrops actually pending. Can't be more than the tokens left in the raffle.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_pending_tokens(&mut self, value: u32) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            value as u64 <= self.raffle.len(),
+            format!("Only {} tokens are left in the raffle", self.raffle.len())
+        );
+        log!(
+            "Pending tokens changed from {} to {}",
+            self.pending_tokens,
+            value
+        );
+        self.pending_tokens = value;
+        true
+    }
+
+    /// Register the `(token_id, media_hash, reference_hash)` of tokens to be minted.
+    /// Tokens without registered hashes are minted without them.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_token_hashes(
+        &mut self,
+        entries: Vec<(TokenId, Base64VecU8, Base64VecU8)>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        entries
+            .into_iter()
+            .for_each(|(token_id, media_hash, reference_hash)| {
+                self.token_hashes
+                    .insert(&token_id, &(media_hash, reference_hash));
+            });
+        true
+    }
+
+    /// Register the `extra` JSON, e.g. traits, of tokens to be minted.
+    /// Each entry is limited to `MAX_TOKEN_EXTRA_LEN` bytes.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_token_extra(&mut self, entries: Vec<(TokenId, String)>) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        entries.into_iter().for_each(|(token_id, extra)| {
+            require!(
+                extra.len() <= MAX_TOKEN_EXTRA_LEN,
+                format!(
+                    "extra of token {} is longer than {} bytes",
+                    token_id, MAX_TOKEN_EXTRA_LEN
+                )
+            );
+            self.token_extras.insert(&token_id, &extra);
+        });
+        true
+    }
+
+    /// Approve `account_id`, e.g. a marketplace, for every token at mint so it can be listed
+    /// right away. None, means no approval. The minter pays for the approval's storage.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_auto_approve_account(&mut self, account_id: Option<AccountId>) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.auto_approve_account = account_id;
+        true
+    }
+
+    /// Register the media of tokens to be minted as data URIs, e.g. `data:image/png;base64,...`.
+    /// Only used with `on_chain_media`. Each entry is limited to `MAX_TOKEN_MEDIA_LEN` bytes.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_token_media(&mut self, entries: Vec<(TokenId, String)>) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        entries.into_iter().for_each(|(token_id, media)| {
+            require!(
+                media.starts_with("data:"),
+                format!("media of token {} is not a data URI", token_id)
+            );
+            require!(
+                media.len() <= MAX_TOKEN_MEDIA_LEN,
+                format!(
+                    "media of token {} is longer than {} bytes",
+                    token_id, MAX_TOKEN_MEDIA_LEN
+                )
+            );
+            self.token_media.insert(&token_id, &media);
+        });
+        true
+    }
+
+    /// Embed the media registered with `admin_set_token_media` in the metadata of minted tokens,
+    /// instead of linking to `{id}.{media_extension}`. Only possible while metadata isn't frozen.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_on_chain_media(&mut self, on_chain_media: bool) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(!self.metadata_frozen, "Metadata is frozen");
+        self.on_chain_media = on_chain_media;
+        true
+    }
+
+    /// Freeze the token metadata format. Can't be undone.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_freeze_metadata(&mut self) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.metadata_frozen = true;
+        true
+    }
+
+    /// Replace the contract metadata returned by `nft_metadata`
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_update_contract_metadata(&mut self, metadata: NFTContractMetadata) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+        self.emit_event(ContractMetadataUpdated {
+            name: &metadata.name,
+            symbol: &metadata.symbol,
+            base_uri: metadata.base_uri.as_deref(),
+        });
+        true
+    }
+
+    /// Media and reference shown for every token until `admin_reveal` is called.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_placeholder(
+        &mut self,
+        placeholder_media: String,
+        placeholder_reference: Option<String>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(!self.revealed, "Collection is already revealed");
+        self.placeholder_media = Some(placeholder_media);
+        self.placeholder_reference = placeholder_reference;
+        true
+    }
+
+    /// Show the real media and reference of every token. Can't be undone.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_reveal(&mut self) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.revealed = true;
+        log!("Collection revealed");
+        true
+    }
+
+    /// Turn informational `tenk` events on or off. Standard NEP-171 events are always emitted.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_events_enabled(&mut self, events_enabled: bool) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_TOKENS);
+        self.events_enabled = events_enabled;
+        true
+    }
+
+    /// Only allow the owner and admins to transfer tokens until `until`. None, means unlocked.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_transfer_lock(&mut self, until: Option<TimestampMs>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_TOKENS);
+        self.transfers_locked_until = until;
+        true
+    }
+
+    /// Emergency stop: until unfrozen only the owner and admins can mint, transfer or approve tokens
     /// @allow ["::admins", "::owner"]
-    pub fn add_whitelist_accounts(&mut self, accounts: Vec<AccountId>, allowance: Option<u32>) -> bool {
-        #[cfg(feature = "testnet")]
-        self.assert_owner_or_admin();
+    #[payable]
+    pub fn admin_freeze_all(&mut self) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_TOKENS);
+        self.frozen = true;
+        true
+    }
+
+    /// Lift the freeze set with `admin_freeze_all`
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_unfreeze_all(&mut self) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_TOKENS);
+        self.frozen = false;
+        true
+    }
+
+    /// Let `account_id` mint its next `count` tokens without paying the price, only the storage.
+    /// Replaces any previous grant, 0 revokes it. Sale status and whitelist allowance still apply.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_grant_free_mints(&mut self, account_id: AccountId, count: u32) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        if count == 0 {
+            self.free_mint_list.remove(&account_id);
+        } else {
+            self.free_mint_list.insert(&account_id, &count);
+        }
+        true
+    }
+
+    /// Allow or forbid paying with cheddar
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_accepts_cheddar(&mut self, accepts_cheddar: bool) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.accepts_cheddar = accepts_cheddar;
+        true
+    }
+
+    /// Mint to and charge the predecessor instead of the signer, see `minter_account`
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_use_predecessor(&mut self, use_predecessor: bool) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.sale.use_predecessor = use_predecessor;
+        true
+    }
+
+    /// Whether burned token ids go back to the raffle. See `nft_burn` for the supply implications.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_reopen_on_burn(&mut self, reopen_on_burn: bool) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.reopen_on_burn = reopen_on_burn;
+        true
+    }
+
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn add_whitelist_accounts(
+        &mut self,
+        accounts: Vec<AccountId>,
+        allowance: Option<u32>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
         let allowance = allowance.unwrap_or_else(|| self.sale.allowance.unwrap_or(0));
         accounts.iter().for_each(|account_id| {
             self.whitelist.insert(account_id, &allowance);
@@ -60,8 +578,14 @@ impl Contract {
     }
 
     /// @allow ["::admins", "::owner"]
-    pub fn update_whitelist_accounts(&mut self, accounts: Vec<AccountId>, allowance_increase: u32) -> bool {
-        self.assert_owner_or_admin();
+    #[payable]
+    pub fn update_whitelist_accounts(
+        &mut self,
+        accounts: Vec<AccountId>,
+        allowance_increase: u32,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
         accounts.iter().for_each(|account_id| {
             let allowance = self.whitelist.get(&account_id).unwrap_or(0) + allowance_increase;
             self.whitelist.insert(account_id, &allowance);
@@ -69,11 +593,207 @@ impl Contract {
         true
     }
 
+    /// Add `additional` token ids to the collection, following the current ones.
+    /// Only possible before the first token is minted.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_extend_raffle(&mut self, additional: u32) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.counter == 0,
+            "Collection can't be extended once minting started"
+        );
+        self.raffle.extend(additional as u64);
+        self.initial_size += additional;
+        true
+    }
+
+    /// Mint every id in `[start, end)` to `receiver_id`, removing them from the raffle.
+    /// Fails if any of the ids was already drawn.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_reserve_range(
+        &mut self,
+        start: u32,
+        end: u32,
+        receiver_id: AccountId,
+    ) -> Vec<Token> {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(start < end, "start must be lower than end");
+        require!(
+            end - start <= MAX_RESERVE_RANGE,
+            format!("Can't reserve more than {} ids at once", MAX_RESERVE_RANGE)
+        );
+        self.mint_ids((start as u64..end as u64).collect(), &receiver_id)
+    }
+
+    /// Mint the tokens `token_ids` to `recipient`, e.g. for a curated drop, removing them from
+    /// the raffle. Fails if any of the ids was already drawn.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_mint_specific(&mut self, token_ids: Vec<u64>, recipient: AccountId) -> Vec<Token> {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            token_ids.len() <= MAX_RESERVE_RANGE as usize,
+            format!("Can't mint more than {} ids at once", MAX_RESERVE_RANGE)
+        );
+        self.mint_ids(token_ids, &recipient)
+    }
+
+    /// Mint one token to each of up to `MAX_AIRDROP_BATCH` recipients for free
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_airdrop(&mut self, recipients: Vec<AccountId>) -> Vec<Token> {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            recipients.len() <= MAX_AIRDROP_BATCH,
+            format!(
+                "Can't airdrop to more than {} accounts at once",
+                MAX_AIRDROP_BATCH
+            )
+        );
+        let left = self.tokens_left();
+        require!(
+            recipients.len() as u32 <= left,
+            format!("Not NFTs left to mint, remaining nfts: {}", left)
+        );
+        let tokens: Vec<Token> = recipients
+            .into_iter()
+            .map_while(|receiver_id| self.draw_and_mint(receiver_id, None))
+            .collect();
+        self.increase_counter(tokens.len() as u32);
+        log_mints(&tokens);
+        tokens
+    }
+
+    /// Mint each recipient its number of tokens for free, up to `MAX_AIRDROP_BATCH` tokens in
+    /// total. Nothing is minted unless there are enough tokens left for all of them.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_airdrop_many(&mut self, entries: Vec<(AccountId, u32)>) -> Vec<Token> {
+        assert_one_yocto();
+        self.assert_owner();
+        let total: u64 = entries.iter().map(|(_, num)| *num as u64).sum();
+        require!(
+            total <= MAX_AIRDROP_BATCH as u64,
+            format!(
+                "Can't airdrop more than {} tokens at once",
+                MAX_AIRDROP_BATCH
+            )
+        );
+        let left = self.tokens_left();
+        require!(
+            total <= left as u64,
+            format!("Not NFTs left to mint, remaining nfts: {}", left)
+        );
+        entries
+            .into_iter()
+            .filter(|(_, num)| *num > 0)
+            .flat_map(|(receiver_id, num)| {
+                self.nft_mint_many_ungaurded(num, &receiver_id, true, false)
+            })
+            .collect()
+    }
+
+    /// Set the Merkle root of the allowlist used by `nft_mint_with_proof`. None, disables it.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_allowlist_root(&mut self, root: Option<Base64VecU8>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        self.allowlist_root = root.map(|root| {
+            root.0
+                .try_into()
+                .unwrap_or_else(|_| env::panic_str("allowlist root must be 32 bytes"))
+        });
+        true
+    }
+
+    /// Whitelist up to `MAX_WHITELIST_BATCH` accounts with the given allowance
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_add_whitelist(&mut self, accounts: Vec<AccountId>, allowance: u32) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        assert_whitelist_batch(accounts.len());
+        accounts.iter().for_each(|account_id| {
+            self.whitelist.insert(account_id, &allowance);
+        });
+        log!("Added {} accounts to the whitelist", accounts.len());
+        true
+    }
+
+    /// Whitelist up to `MAX_WHITELIST_BATCH` accounts with `allowance` and split the attached
+    /// deposit evenly between them to prepay the storage of their mints, so they only need
+    /// cheddar to mint.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_add_whitelist_with_storage(
+        &mut self,
+        accounts: Vec<AccountId>,
+        allowance: u32,
+    ) -> bool {
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        assert_whitelist_batch(accounts.len());
+        require!(!accounts.is_empty(), "accounts can't be empty");
+        let share = env::attached_deposit() / accounts.len() as Balance;
+        require!(
+            share >= self.token_storage_cost().0 * allowance as Balance,
+            "Attached deposit doesn't cover the storage of each account's allowance"
+        );
+        accounts.iter().for_each(|account_id| {
+            self.whitelist.insert(account_id, &allowance);
+            let deposit = self.storage_deposits.get(account_id).unwrap_or_default() + share;
+            self.storage_deposits.insert(account_id, &deposit);
+        });
+        self.storage_deposits_total += share * accounts.len() as Balance;
+        log!(
+            "Added {} accounts to the whitelist with {} yoctoNEAR of storage each",
+            accounts.len(),
+            share
+        );
+        true
+    }
+
+    /// Whitelist up to `MAX_WHITELIST_BATCH` accounts, each with its own allowance
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_add_whitelist_tiered(&mut self, entries: Vec<(AccountId, u32)>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        assert_whitelist_batch(entries.len());
+        entries.iter().for_each(|(account_id, allowance)| {
+            self.whitelist.insert(account_id, allowance);
+        });
+        log!("Added {} accounts to the whitelist", entries.len());
+        true
+    }
+
+    /// Remove up to `MAX_WHITELIST_BATCH` accounts from the whitelist
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_remove_whitelist(&mut self, accounts: Vec<AccountId>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_WHITELIST);
+        assert_whitelist_batch(accounts.len());
+        let removed = accounts
+            .iter()
+            .filter(|account_id| self.whitelist.remove(account_id).is_some())
+            .count();
+        log!("Removed {} accounts from the whitelist", removed);
+        true
+    }
+
     /// End public sale/minting, going back to the pre-presale state in which no one can mint.
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn close_sale(&mut self) -> bool {
-        #[cfg(not(feature = "testnet"))]
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
         self.sale.presale_start = None;
         self.sale.public_sale_start = None;
         true
@@ -85,56 +805,1338 @@ impl Contract {
     /// Note: you most likely won't need to call this since the presale
     /// starts automatically based on time.
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn start_presale(
         &mut self,
         public_sale_start: Option<TimestampMs>,
         presale_price: Option<U128>,
     ) -> bool {
-        #[cfg(not(feature = "testnet"))]
-        self.assert_owner_or_admin();
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        if presale_price.is_some() {
+            self.set_presale_price(presale_price);
+        }
         let current_time = current_time_ms();
         self.sale.presale_start = Some(current_time);
         self.sale.public_sale_start = public_sale_start;
-        if presale_price.is_some() {
-            self.sale.presale_price = presale_price;
-        }
+        self.sale.validate();
         true
     }
 
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn start_sale(&mut self, price: Option<YoctoNEAR>) -> bool {
-        #[cfg(not(feature = "testnet"))]
-        self.assert_owner_or_admin();
-        self.sale.public_sale_start = Some(current_time_ms());
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        // set before opening the sale, `freeze_price_on_open` applies once it's open
         if let Some(price) = price {
-            self.sale.price = price
+            self.set_sale_price(price);
         }
+        self.sale.public_sale_start = Some(current_time_ms());
+        self.sale.validate();
+        true
+    }
+
+    /// Reschedule the presale and public sale. None, means never.
+    /// Neither start can be more than a few minutes in the past.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_sale_times(
+        &mut self,
+        presale_start: Option<TimestampMs>,
+        public_sale_start: Option<TimestampMs>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        let earliest = current_time_ms().saturating_sub(SALE_START_TOLERANCE_MS);
+        [presale_start, public_sale_start]
+            .iter()
+            .flatten()
+            .for_each(|start| require!(*start >= earliest, "sale start is in the past"));
+        self.sale.presale_start = presale_start;
+        self.sale.public_sale_start = public_sale_start;
+        self.sale.validate();
+        true
+    }
+
+    /// Limit the number of tokens minted during the presale. None, means no limit.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_presale_supply_cap(&mut self, presale_supply_cap: Option<u32>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.presale_supply_cap = presale_supply_cap;
+        true
+    }
+
+    /// Share in basis points of the price paid to the referrer of a mint. None, means no bonus.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_referral_bps(&mut self, referral_bps: Option<u16>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.referral_bps = referral_bps;
+        self.sale.validate();
+        true
+    }
+
+    /// Min time in milliseconds between two mints of an account. None, means no cooldown.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_mint_cooldown(&mut self, mint_cooldown_ms: Option<u64>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.mint_cooldown_ms = mint_cooldown_ms;
+        true
+    }
+
+    /// Reject mints attaching more than `max_overpay_multiplier` times the cost and storage,
+    /// instead of refunding the excess. None, means any overpay is refunded.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_max_overpay_multiplier(
+        &mut self,
+        max_overpay_multiplier: Option<u32>,
+    ) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.max_overpay_multiplier = max_overpay_multiplier;
+        self.sale.validate();
+        true
+    }
+
+    /// End the sale at `public_sale_end`, after which nothing can be minted. None, means no end.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_sale_end(&mut self, public_sale_end: Option<TimestampMs>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.public_sale_end = public_sale_end;
+        self.sale.validate();
         true
     }
 
     /// Add a new admin. Careful who you add!
+    /// `permissions` are `Permission` flags, by default all of them. Admins can only grant the
+    /// permissions they have.
     /// @allow ["::admins", "::owner"]
-    pub fn add_admin(&mut self, account_id: AccountId) -> bool {
-        self.assert_owner_or_admin();
+    #[payable]
+    pub fn add_admin(&mut self, account_id: AccountId, permissions: Option<u8>) -> bool {
+        assert_one_yocto();
+        let permissions = permissions.unwrap_or(Permission::ALL);
+        require!(permissions != 0, "An admin needs at least one permission");
+        require!(
+            permissions & !Permission::ALL == 0,
+            "Unknown permission flags"
+        );
+        self.assert_admin_permission(permissions);
+        require!(
+            !self.is_admin(&account_id) || self.signer_is_owner(),
+            "Only the owner can change the permissions of an admin"
+        );
         self.admins.insert(&account_id);
+        self.admin_permissions.insert(&account_id, &permissions);
         true
     }
 
-    /// Update public sale price. 
+    /// Remove an admin along with its permissions
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn remove_admin(&mut self, account_id: AccountId) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        self.admins.remove(&account_id);
+        self.admin_permissions.remove(&account_id);
+        true
+    }
+
+    /// Update public sale price, same as `admin_set_sale_price`.
     /// Careful this is in yoctoNear: 1N = 1000000000000000000000000 yN
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_price(&mut self, price: U128) -> bool {
-        self.assert_owner_or_admin();
-        self.sale.price = price;
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.set_sale_price(price);
         true
     }
 
-    /// Update the presale price
+    /// Update the presale price, same as `admin_set_presale_price`.
     /// Careful this is in yoctoNear: 1N = 1000000000000000000000000 yN
     /// @allow ["::admins", "::owner"]
+    #[payable]
     pub fn update_presale_price(&mut self, presale_price: Option<U128>) -> bool {
-        self.assert_owner_or_admin();
-        self.sale.presale_price = presale_price;
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.set_presale_price(presale_price);
+        true
+    }
+
+    /// Set the public sale price and emit a `sale_price_changed` event.
+    /// Fails if `freeze_price_on_open` is set and the public sale is already open.
+    /// Careful this is in yoctoNear: 1N = 1000000000000000000000000 yN
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_sale_price(&mut self, price: U128) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.set_sale_price(price);
         true
     }
+
+    /// Set the max number of tokens minted in one transaction. None, means unlimited.
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_mint_rate_limit(&mut self, limit: Option<u32>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.sale.mint_rate_limit = limit;
+        self.sale.validate();
+        true
+    }
+
+    /// Set the presale price and emit a `sale_price_changed` event. None, means the
+    /// public sale price is used during presale.
+    /// Careful this is in yoctoNear: 1N = 1000000000000000000000000 yN
+    /// @allow ["::admins", "::owner"]
+    #[payable]
+    pub fn admin_set_presale_price(&mut self, price: Option<U128>) -> bool {
+        assert_one_yocto();
+        self.assert_admin_permission(Permission::MANAGE_SALE);
+        self.set_presale_price(price);
+        true
+    }
+}
+
+// The seed can be read from the contract's state, so seeded draws are only for testnet dry runs
+#[cfg(feature = "testnet")]
+#[near_bindgen]
+impl Contract {
+    /// Fix the seed of the raffle so that the draw order is reproducible, for dry runs.
+    /// Anyone knowing the seed can predict the draws. Only possible before the first mint.
+    /// @allow ["::owner"]
+    #[payable]
+    pub fn admin_set_raffle_seed(&mut self, seed: Base64VecU8) -> bool {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(self.counter == 0, "Tokens were already minted");
+        require!(!seed.0.is_empty(), "seed can't be empty");
+        self.raffle_seed = Some(seed);
+        true
+    }
+}
+
+fn assert_whitelist_batch(len: usize) {
+    require!(
+        len <= MAX_WHITELIST_BATCH,
+        format!(
+            "Can't update more than {} whitelist accounts at once",
+            MAX_WHITELIST_BATCH
+        )
+    );
+}
+
+impl Contract {
+    fn assert_price_not_frozen(&self) {
+        require!(
+            !(self.sale.freeze_price_on_open && matches!(self.get_status(), Status::Open)),
+            "Price is frozen since the sale is open"
+        );
+    }
+
+    /// Change the public sale price, unless it's frozen, and emit a `sale_price_changed` event
+    fn set_sale_price(&mut self, price: U128) {
+        self.assert_price_not_frozen();
+        self.sale.price = price;
+        self.sale.validate();
+        self.log_sale_price_changed();
+    }
+
+    /// Change the presale price, unless it's frozen, and emit a `sale_price_changed` event
+    fn set_presale_price(&mut self, price: Option<U128>) {
+        self.assert_price_not_frozen();
+        self.sale.presale_price = price;
+        self.sale.validate();
+        self.log_sale_price_changed();
+    }
+
+    fn log_sale_price_changed(&self) {
+        self.emit_event(SalePriceChanged {
+            price: self.sale.price,
+            presale_price: self.sale.presale_price,
+        });
+    }
+}
+
+impl Contract {
+    /// Count the owners of up to `limit` tokens after `from` which weren't counted yet
+    fn count_holders(&mut self, from: Option<TokenId>, limit: u64) -> HoldersCount {
+        let owners: Vec<(TokenId, AccountId)> = match from {
+            Some(from) => self
+                .tokens
+                .owner_by_id
+                .iter_from(from)
+                .take(limit as usize)
+                .collect(),
+            None => self
+                .tokens
+                .owner_by_id
+                .iter()
+                .take(limit as usize)
+                .collect(),
+        };
+        for (_, owner_id) in owners.iter() {
+            if self.counted_holders.insert(owner_id) {
+                self.num_holders += 1;
+            }
+        }
+        match owners.last() {
+            Some((token_id, _)) if owners.len() as u64 == limit => {
+                HoldersCount::Counting(Some(token_id.clone()))
+            }
+            _ => HoldersCount::Clearing,
+        }
+    }
+
+    /// Remove up to `limit` accounts from `counted_holders`, `None` once it's empty
+    fn forget_counted_holders(&mut self, limit: u64) -> Option<HoldersCount> {
+        let accounts = self.counted_holders.as_vector();
+        let last: Vec<AccountId> = (accounts.len().saturating_sub(limit)..accounts.len())
+            .filter_map(|index| accounts.get(index))
+            .collect();
+        for account_id in last.iter() {
+            self.counted_holders.remove(account_id);
+        }
+        if self.counted_holders.is_empty() {
+            None
+        } else {
+            Some(HoldersCount::Clearing)
+        }
+    }
+
+    /// Take `ids` out of the raffle and mint them to `receiver_id`. Tokens held for pending
+    /// linkdrops can't be taken.
+    fn mint_ids(&mut self, ids: Vec<u64>, receiver_id: &AccountId) -> Vec<Token> {
+        require!(
+            self.raffle_index_from.is_none(),
+            "The raffle isn't indexed yet, call admin_index_raffle until it returns 0"
+        );
+        let left = self.tokens_left();
+        require!(
+            ids.len() as u32 <= left,
+            format!("Not NFTs left to mint, remaining nfts: {}", left)
+        );
+        let tokens: Vec<Token> = ids
+            .into_iter()
+            .map(|id| {
+                require!(
+                    self.raffle.take(id),
+                    format!("Token {} was already drawn", id)
+                );
+                self.mint_drawn(id, receiver_id.clone(), None)
+            })
+            .collect();
+        self.increase_counter(tokens.len() as u32);
+        log_mint(receiver_id, &tokens);
+        tokens
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{test_utils::get_logs, testing_env};
+    use std::collections::HashMap;
+
+    #[test]
+    fn set_sale_price() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_price(U128(2 * PRICE));
+        assert_eq!(contract.sale.price.0, 2 * PRICE);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"sale_price_changed","data":[{{"price":"{}","presale_price":null}}]}}"#,
+                2 * PRICE
+            )]
+        );
+        contract.admin_set_presale_price(Some(U128(PRICE)));
+        assert_eq!(contract.sale.presale_price, Some(U128(PRICE)));
+    }
+
+    #[test]
+    fn events_disabled() {
+        let mut contract = new_contract();
+        contract.admin_set_events_enabled(false);
+        contract.admin_set_sale_price(U128(2 * PRICE));
+        assert!(get_logs().is_empty());
+
+        set_context(&alice(), 100 * PRICE);
+        let token = contract.nft_mint_one(false);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{{"owner_id":"alice.near","token_ids":["{}"]}}]}}"#,
+                token.token_id
+            )
+        );
+        assert!(get_logs()
+            .iter()
+            .all(|log| !log.contains(r#""standard":"tenk""#)));
+    }
+
+    #[test]
+    #[should_panic(expected = "price must be positive")]
+    fn set_sale_price_zero() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_price(U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "presale price must be positive")]
+    fn set_presale_price_zero() {
+        let mut contract = new_contract();
+        contract.admin_set_presale_price(Some(U128(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method is private to owner or admin")]
+    fn set_sale_price_not_admin() {
+        let mut contract = new_contract();
+        set_context(&alice(), 1);
+        contract.admin_set_sale_price(U128(PRICE));
+    }
+
+    #[test]
+    #[should_panic(expected = "Price is frozen since the sale is open")]
+    fn set_sale_price_frozen() {
+        let mut sale = open_sale();
+        sale.freeze_price_on_open = true;
+        let mut contract = new_contract_with_sale(sale);
+        contract.admin_set_sale_price(U128(PRICE));
+    }
+
+    #[test]
+    #[should_panic(expected = "Price is frozen since the sale is open")]
+    fn update_price_frozen() {
+        let mut sale = open_sale();
+        sale.freeze_price_on_open = true;
+        let mut contract = new_contract_with_sale(sale);
+        contract.update_price(U128(PRICE));
+    }
+
+    #[test]
+    #[should_panic(expected = "Price is frozen since the sale is open")]
+    fn start_sale_price_frozen() {
+        let mut sale = open_sale();
+        sale.freeze_price_on_open = true;
+        let mut contract = new_contract_with_sale(sale);
+        contract.start_sale(Some(U128(2 * PRICE)));
+    }
+
+    #[test]
+    #[should_panic(expected = "price must be positive")]
+    fn update_price_zero() {
+        let mut contract = new_contract();
+        contract.update_price(U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "presale price can't be higher than the public sale price")]
+    fn update_presale_price_above_price() {
+        let mut contract = new_contract();
+        contract.update_presale_price(Some(U128(2 * PRICE)));
+    }
+
+    #[test]
+    #[should_panic(expected = "presale price can't be higher than the public sale price")]
+    fn start_presale_price_above_price() {
+        let mut contract = new_contract();
+        contract.start_presale(None, Some(U128(2 * PRICE)));
+    }
+
+    #[test]
+    fn start_sale_with_price() {
+        let mut sale = open_sale();
+        sale.public_sale_start = None;
+        sale.freeze_price_on_open = true;
+        let mut contract = new_contract_with_sale(sale);
+        contract.start_sale(Some(U128(2 * PRICE)));
+        assert_eq!(contract.sale.price.0, 2 * PRICE);
+        assert!(matches!(contract.get_status(), Status::Open));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"sale_price_changed","data":[{{"price":"{}","presale_price":null}}]}}"#,
+                2 * PRICE
+            )]
+        );
+    }
+
+    fn accounts(num: usize) -> Vec<AccountId> {
+        (0..num)
+            .map(|i| AccountId::new_unchecked(format!("user{}.near", i)))
+            .collect()
+    }
+
+    #[test]
+    fn add_whitelist() {
+        let mut contract = new_contract();
+        let accounts = accounts(50);
+        contract.admin_add_whitelist(accounts.clone(), 3);
+        assert_eq!(get_logs(), vec!["Added 50 accounts to the whitelist"]);
+        assert_eq!(contract.remaining_allowance(&accounts[0]), Some(3));
+        assert_eq!(contract.remaining_allowance(&accounts[49]), Some(3));
+        assert_eq!(contract.remaining_allowance(&alice()), None);
+
+        contract.admin_remove_whitelist(accounts[..10].to_vec());
+        assert!(!contract.whitelisted(&accounts[0]));
+        assert!(contract.whitelisted(&accounts[10]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't update more than 200 whitelist accounts at once")]
+    fn add_whitelist_too_many() {
+        let mut contract = new_contract();
+        contract.admin_add_whitelist(accounts(MAX_WHITELIST_BATCH + 1), 1);
+    }
+
+    #[test]
+    fn extend_raffle() {
+        let mut contract = new_contract();
+        contract.admin_extend_raffle(5);
+        assert_eq!(contract.initial(), SIZE as u64 + 5);
+        assert_eq!(contract.tokens_left(), SIZE + 5);
+        let mut ids: Vec<u64> = contract
+            .nft_mint_many_ungaurded(SIZE + 5, &alice(), true, false)
+            .iter()
+            .map(|token| token.token_id.parse().unwrap())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..SIZE as u64 + 5).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Collection can't be extended once minting started")]
+    fn extend_raffle_after_mint() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_extend_raffle(5);
+    }
+
+    #[test]
+    fn airdrop() {
+        let mut contract = new_contract();
+        let recipients = accounts(3);
+        let tokens = contract.admin_airdrop(recipients.clone());
+        assert_eq!(tokens.len(), 3);
+        let mint_logs: Vec<String> = get_logs()
+            .into_iter()
+            .filter(|log| log.contains("\"nft_mint\""))
+            .collect();
+        assert_eq!(mint_logs.len(), 1);
+        assert!(mint_logs[0].contains("user2.near"));
+        for account_id in recipients {
+            assert_eq!(contract.nft_supply_for_owner(account_id).0, 1);
+        }
+        assert_eq!(contract.nft_total_minted(), 3);
+        assert_eq!(contract.tokens_left(), SIZE - 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't airdrop to more than 100 accounts at once")]
+    fn airdrop_too_many() {
+        let mut contract = new_contract();
+        contract.admin_airdrop(accounts(MAX_AIRDROP_BATCH + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not NFTs left to mint, remaining nfts: 20")]
+    fn airdrop_over_supply() {
+        let mut contract = new_contract();
+        contract.admin_airdrop(accounts(SIZE as usize + 1));
+    }
+
+    #[test]
+    fn airdrop_implicit_account() {
+        let mut contract = new_contract();
+        let implicit: AccountId = "ab".repeat(32).parse().unwrap();
+        contract.admin_airdrop(vec![implicit.clone()]);
+        assert_eq!(contract.nft_supply_for_owner(implicit).0, 1);
+    }
+
+    #[test]
+    fn airdrop_malformed_receiver() {
+        // one malformed id fails deserializing `recipients`, so nothing is airdropped
+        let recipients = r#"["bob.near", "Alice.near"]"#;
+        let err = near_sdk::serde_json::from_str::<Vec<AccountId>>(recipients).unwrap_err();
+        assert!(err.to_string().contains("the account ID is invalid"));
+    }
+
+    #[test]
+    fn airdrop_many() {
+        let mut contract = new_contract();
+        let tokens = contract.admin_airdrop_many(vec![(alice(), 3), (bob(), 1), (owner(), 0)]);
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 3);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 1);
+        assert_eq!(contract.nft_supply_for_owner(owner()).0, 0);
+        assert_eq!(contract.tokens_left(), SIZE - 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not NFTs left to mint, remaining nfts: 20")]
+    fn airdrop_many_over_supply() {
+        let mut contract = new_contract();
+        contract.admin_airdrop_many(vec![(alice(), SIZE - 1), (bob(), 2)]);
+    }
+
+    #[test]
+    fn add_whitelist_tiered() {
+        let mut contract = new_contract_with_sale(presale());
+        contract.admin_add_whitelist_tiered(vec![(alice(), 3), (bob(), 1)]);
+        assert_eq!(contract.remaining_allowance(&alice()), Some(3));
+        assert_eq!(contract.remaining_allowance(&bob()), Some(1));
+
+        set_context(&alice(), 100 * PRICE);
+        assert_eq!(contract.nft_mint_many(false, 2, None, None).len(), 2);
+        assert_eq!(contract.nft_mint_many(false, 2, None, None).len(), 1);
+        assert_eq!(contract.remaining_allowance(&alice()), Some(0));
+
+        set_context(&bob(), 100 * PRICE);
+        assert_eq!(contract.nft_mint_many(false, 2, None, None).len(), 1);
+        assert_eq!(contract.remaining_allowance(&bob()), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Account has no more allowance left")]
+    fn add_whitelist_tiered_used_up() {
+        let mut contract = new_contract_with_sale(presale());
+        contract.admin_add_whitelist_tiered(vec![(alice(), 1)]);
+        set_context(&alice(), 100 * PRICE);
+        contract.nft_mint_one(false);
+        contract.nft_mint_one(false);
+    }
+
+    #[cfg(feature = "testnet")]
+    #[test]
+    fn raffle_seed() {
+        let mut contract = new_contract();
+        contract.admin_set_raffle_seed(b"seed".to_vec().into());
+        let minted: Vec<u64> = contract
+            .nft_mint_many_ungaurded(SIZE, &alice(), true, false)
+            .iter()
+            .map(|token| token.token_id.parse().unwrap())
+            .collect();
+        // the same seed gives the same draws, whatever the block's random seed
+        testing_env!(context(&owner()).random_seed([7; 32]).build());
+        let mut raffle = Raffle::new(b"seeded".to_vec(), SIZE as u64);
+        let expected: Vec<u64> = (0..SIZE).map(|_| raffle.draw(Some(b"seed"))).collect();
+        assert_eq!(minted, expected);
+    }
+
+    #[cfg(feature = "testnet")]
+    #[test]
+    #[should_panic(expected = "Tokens were already minted")]
+    fn raffle_seed_after_mint() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_set_raffle_seed(b"seed".to_vec().into());
+    }
+
+    #[test]
+    fn metadata_extensions() {
+        let mut contract = new_contract();
+        contract.admin_set_metadata_extensions("gif".to_string(), "txt".to_string());
+        set_context(&owner(), ONE_NEAR);
+        let token = contract.nft_mint_one(false);
+        let metadata = token.metadata.unwrap();
+        assert_eq!(metadata.media, Some(format!("{}.gif", token.token_id)));
+        assert_eq!(metadata.reference, Some(format!("{}.txt", token.token_id)));
+    }
+
+    #[test]
+    fn copies() {
+        let mut contract = new_contract();
+        contract.admin_set_copies(Some(SIZE as u64));
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        assert!(tokens
+            .iter()
+            .all(|token| token_metadata(&contract, &token.token_id).copies == Some(SIZE as u64)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens were already minted")]
+    fn metadata_extensions_after_mint() {
+        let mut contract = new_contract();
+        set_context(&owner(), ONE_NEAR);
+        contract.nft_mint_one(false);
+        set_context(&owner(), 1);
+        contract.admin_set_metadata_extensions("gif".to_string(), "txt".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata is frozen")]
+    fn metadata_extensions_frozen() {
+        let mut contract = new_contract();
+        contract.admin_freeze_metadata();
+        contract.admin_set_metadata_extensions("gif".to_string(), "txt".to_string());
+    }
+
+    #[test]
+    fn token_extra() {
+        let mut contract = new_contract();
+        let extra = r#"{"rarity":"legendary"}"#.to_string();
+        contract.admin_set_token_extra(vec![("5".to_string(), extra.clone())]);
+        contract.admin_reserve_range(4, 6, alice());
+        assert_eq!(token_metadata(&contract, "5").extra, Some(extra));
+        assert_eq!(token_metadata(&contract, "4").extra, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "extra of token 5 is longer than 1024 bytes")]
+    fn token_extra_too_long() {
+        let mut contract = new_contract();
+        contract.admin_set_token_extra(vec![("5".to_string(), "x".repeat(1025))]);
+    }
+
+    #[test]
+    fn on_chain_media() {
+        let mut contract = new_contract();
+        let media = "data:image/png;base64,iVBORw0KGgo=".to_string();
+        contract.admin_set_token_media(vec![("5".to_string(), media.clone())]);
+        contract.admin_set_on_chain_media(true);
+        contract.admin_reserve_range(4, 6, alice());
+        assert_eq!(token_metadata(&contract, "5").media, Some(media));
+        assert_eq!(
+            token_metadata(&contract, "4").media,
+            Some("4.png".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "media of token 5 is not a data URI")]
+    fn on_chain_media_not_data_uri() {
+        let mut contract = new_contract();
+        contract.admin_set_token_media(vec![("5".to_string(), "5.png".to_string())]);
+    }
+
+    #[test]
+    fn token_hashes() {
+        let mut contract = new_contract();
+        let hash = |id: u32, kind: &str| Base64VecU8(format!("{}{}", kind, id).into_bytes());
+        contract.admin_set_token_hashes(
+            (1..SIZE)
+                .map(|id| (id.to_string(), hash(id, "media"), hash(id, "ref")))
+                .collect(),
+        );
+        let tokens = contract.nft_mint_many_ungaurded(SIZE, &owner(), true, false);
+        tokens.into_iter().for_each(|token| {
+            let metadata = token_metadata(&contract, &token.token_id);
+            let id: u32 = token.token_id.parse().unwrap();
+            if id == 0 {
+                assert!(metadata.media_hash.is_none());
+                assert!(metadata.reference_hash.is_none());
+            } else {
+                assert_eq!(metadata.media_hash, Some(hash(id, "media")));
+                assert_eq!(metadata.reference_hash, Some(hash(id, "ref")));
+            }
+        });
+    }
+
+    #[test]
+    fn update_contract_metadata() {
+        let mut contract = new_contract();
+        let mut metadata = contract.nft_metadata();
+        metadata.base_uri = Some("ipfs://new".to_string());
+        contract.admin_update_contract_metadata(metadata);
+        assert_eq!(
+            contract.nft_metadata().base_uri,
+            Some("ipfs://new".to_string())
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"tenk","version":"1.0.0","event":"contract_metadata_updated","data":[{"name":"name","symbol":"sym","base_uri":"ipfs://new"}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Method is private to owner")]
+    fn update_contract_metadata_not_owner() {
+        let mut contract = new_contract();
+        let metadata = contract.nft_metadata();
+        set_context(&alice(), 1);
+        contract.admin_update_contract_metadata(metadata);
+    }
+
+    #[test]
+    fn reveal() {
+        let mut contract = new_contract();
+        contract.admin_set_placeholder("hidden.png".to_string(), Some("hidden.json".to_string()));
+        let token_id = contract.nft_mint_many_ungaurded(1, &alice(), true, false)[0]
+            .token_id
+            .clone();
+
+        let metadata = token_metadata(&contract, &token_id);
+        assert_eq!(metadata.media, Some("hidden.png".to_string()));
+        assert_eq!(metadata.reference, Some("hidden.json".to_string()));
+        let tokens = contract.nft_tokens_for_owner(alice(), None, None);
+        assert_eq!(
+            tokens[0].metadata.as_ref().unwrap().media,
+            Some("hidden.png".to_string())
+        );
+
+        contract.admin_reveal();
+        let metadata = token_metadata(&contract, &token_id);
+        assert_eq!(metadata.media, Some(format!("{}.png", token_id)));
+        assert_eq!(metadata.reference, Some(format!("{}.json", token_id)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Collection is already revealed")]
+    fn placeholder_after_reveal() {
+        let mut contract = new_contract();
+        contract.admin_reveal();
+        contract.admin_set_placeholder("hidden.png".to_string(), None);
+    }
+
+    #[test]
+    fn add_whitelist_with_storage() {
+        let mut contract = new_contract_with_sale(presale());
+        set_context(&owner(), 10 * ONE_NEAR);
+        contract.admin_add_whitelist_with_storage(vec![alice(), bob()], 2);
+        for account_id in [alice(), bob()] {
+            assert!(contract.whitelisted(&account_id));
+            assert_eq!(contract.storage_deposit_of(&account_id).0, 5 * ONE_NEAR);
+        }
+
+        // alice mints with cheddar only, the storage comes from her prepaid deposit
+        contract
+            .cheddar_deposits
+            .insert(&alice(), &(1_000 * ONE_NEAR));
+        set_context(&alice(), 0);
+        contract.nft_mint_one(true);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+        assert!(contract.storage_deposit_of(&alice()).0 < 5 * ONE_NEAR);
+        assert_eq!(contract.remaining_allowance(&alice()), Some(1));
+    }
+
+    #[test]
+    fn grant_free_mints() {
+        let mut contract = new_contract();
+        contract.admin_grant_free_mints(alice(), 2);
+        assert_eq!(contract.free_mints_of(&alice()), 2);
+        assert_eq!(contract.minting_cost(&alice(), 3).0, PRICE);
+
+        // only storage is paid for the free mints
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_mint_one(false);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+        assert_eq!(contract.free_mints_of(&alice()), 0);
+
+        // the third costs full price
+        assert_eq!(contract.minting_cost(&alice(), 1).0, PRICE);
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough deposit to buy")]
+    fn free_mints_used_up() {
+        let mut contract = new_contract();
+        contract.admin_grant_free_mints(alice(), 1);
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_mint_one(false);
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit doesn't cover the storage")]
+    fn add_whitelist_with_storage_not_enough() {
+        let mut contract = new_contract();
+        set_context(&owner(), 1);
+        contract.admin_add_whitelist_with_storage(vec![alice(), bob()], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "over mint limit")]
+    fn set_mint_rate_limit() {
+        let mut contract = new_contract();
+        contract.admin_set_mint_rate_limit(Some(2));
+        assert_eq!(contract.mint_rate_limit(), Some(2));
+        set_context(&alice(), 3 * PRICE + ONE_NEAR);
+        contract.nft_mint_many(false, 3, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "mint rate limit must be positive")]
+    fn set_mint_rate_limit_zero() {
+        let mut contract = new_contract();
+        contract.admin_set_mint_rate_limit(Some(0));
+    }
+
+    #[test]
+    fn reserve_range() {
+        let mut contract = new_contract();
+        let reserved = contract.admin_reserve_range(5, 15, bob());
+        assert_eq!(reserved.len(), 10);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 10);
+        assert_eq!(contract.tokens_left(), SIZE - 10);
+
+        let minted = contract.nft_mint_many_ungaurded(SIZE - 10, &alice(), true, false);
+        assert!(minted.iter().all(|token| {
+            let id: u32 = token.token_id.parse().unwrap();
+            !(5..15).contains(&id)
+        }));
+        assert_eq!(contract.tokens_left(), 0);
+    }
+
+    #[test]
+    fn mint_specific() {
+        let mut contract = new_contract();
+        contract.admin_extend_raffle(30);
+        let tokens = contract.admin_mint_specific(vec![1, 42], bob());
+        let ids: Vec<&str> = tokens.iter().map(|token| token.token_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "42"]);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 2);
+        assert_eq!(contract.tokens_left(), SIZE + 30 - 2);
+        assert!(!contract.raffle.take(1));
+        assert!(!contract.raffle.take(42));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token 3 was already drawn")]
+    fn mint_specific_drawn() {
+        let mut contract = new_contract();
+        contract.admin_mint_specific(vec![3], bob());
+        contract.admin_mint_specific(vec![4, 3], bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "was already drawn")]
+    fn reserve_range_drawn() {
+        let mut contract = new_contract();
+        contract.admin_reserve_range(5, 6, bob());
+        contract.admin_reserve_range(0, 10, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not NFTs left to mint, remaining nfts: 1")]
+    fn reserve_range_pending_linkdrops() {
+        let mut contract = new_contract();
+        contract.pending_tokens = SIZE - 1;
+        contract.admin_reserve_range(0, 2, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not NFTs left to mint, remaining nfts: 0")]
+    fn mint_specific_pending_linkdrops() {
+        let mut contract = new_contract();
+        contract.pending_tokens = SIZE;
+        contract.admin_mint_specific(vec![3], bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't reserve more than 100 ids at once")]
+    fn reserve_range_too_wide() {
+        let mut contract = new_contract();
+        contract.admin_reserve_range(0, 101, bob());
+    }
+
+    #[test]
+    fn set_sale_times() {
+        let mut contract = new_contract();
+        assert!(matches!(contract.get_status(), Status::Open));
+        contract.admin_set_sale_times(Some(NOW + 1_000), Some(NOW + 2_000));
+        assert!(matches!(contract.get_status(), Status::Closed));
+        set_time(&owner(), NOW + 1_500);
+        assert!(matches!(contract.get_status(), Status::Presale));
+        set_time(&owner(), NOW + 2_500);
+        assert!(matches!(contract.get_status(), Status::Open));
+    }
+
+    #[test]
+    fn presale_supply_cap() {
+        let mut contract = new_contract_with_sale(presale());
+        contract.admin_add_whitelist(vec![alice(), bob()], 5);
+        contract.admin_set_presale_supply_cap(Some(3));
+        assert_eq!(contract.presale_tokens_left(), 3);
+
+        set_context(&alice(), 5 * PRICE + ONE_NEAR);
+        assert_eq!(contract.nft_mint_many(false, 5, None, None).len(), 3);
+        assert_eq!(contract.presale_tokens_left(), 0);
+        assert_eq!(contract.tokens_left(), SIZE - 3);
+
+        // public minting continues once the sale opens
+        set_context(&owner(), 1);
+        contract.admin_set_sale_times(Some(NOW - 1), Some(NOW - 1));
+        set_context(&bob(), 2 * PRICE + ONE_NEAR);
+        assert_eq!(contract.nft_mint_many(false, 2, None, None).len(), 2);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Presale supply cap reached")]
+    fn presale_supply_cap_reached() {
+        let mut contract = new_contract_with_sale(presale());
+        contract.admin_add_whitelist(vec![alice(), bob()], 5);
+        contract.admin_set_presale_supply_cap(Some(1));
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        set_context(&bob(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    fn sale_starts_at_exact_time() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_times(Some(NOW + 1_000), Some(NOW + 2_000));
+        set_time(&owner(), NOW + 999);
+        assert!(matches!(contract.get_status(), Status::Closed));
+        set_time(&owner(), NOW + 1_000);
+        assert!(matches!(contract.get_status(), Status::Presale));
+        set_time(&owner(), NOW + 1_999);
+        assert!(matches!(contract.get_status(), Status::Presale));
+        set_time(&owner(), NOW + 2_000);
+        assert!(matches!(contract.get_status(), Status::Open));
+    }
+
+    #[test]
+    fn mint_cooldown() {
+        let mut contract = new_contract();
+        contract.admin_set_mint_cooldown(Some(10_000));
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        testing_env!(context(&alice())
+            .block_timestamp((NOW + 10_000) * 1_000_000)
+            .attached_deposit(PRICE + ONE_NEAR)
+            .build());
+        contract.nft_mint_one(false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+
+        // the owner is exempt
+        set_context(&owner(), ONE_NEAR);
+        contract.nft_mint_one(false);
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mint cooldown, try again in 9 seconds")]
+    fn mint_during_cooldown() {
+        let mut contract = new_contract();
+        contract.admin_set_mint_cooldown(Some(10_000));
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        testing_env!(context(&alice())
+            .block_timestamp((NOW + 1_500) * 1_000_000)
+            .attached_deposit(PRICE + ONE_NEAR)
+            .build());
+        contract.nft_mint_one(false);
+    }
+
+    #[test]
+    fn sale_end() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_end(Some(NOW + 1_000));
+        assert_eq!(contract.get_sale_info().sale_end, NOW + 1_000);
+        set_time(&owner(), NOW + 1_000);
+        assert!(matches!(contract.get_status(), Status::Open));
+        set_time(&owner(), NOW + 1_001);
+        assert!(matches!(contract.get_status(), Status::Ended));
+        assert!(!contract.mint_live());
+        assert_eq!(contract.effective_batch_limit(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "sale must end after it starts")]
+    fn sale_end_before_start() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_times(None, Some(NOW + 2_000));
+        contract.admin_set_sale_end(Some(NOW + 1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "presale must start before the public sale")]
+    fn set_sale_times_presale_after_public() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_times(Some(NOW + 2_000), Some(NOW + 1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "sale start is in the past")]
+    fn set_sale_times_in_the_past() {
+        let mut contract = new_contract();
+        contract.admin_set_sale_times(None, Some(NOW - SALE_START_TOLERANCE_MS - 1));
+    }
+
+    #[test]
+    fn set_sale_price_frozen_before_open() {
+        let mut sale = open_sale();
+        sale.public_sale_start = None;
+        sale.freeze_price_on_open = true;
+        let mut contract = new_contract_with_sale(sale);
+        contract.admin_set_sale_price(U128(2 * PRICE));
+        assert_eq!(contract.sale.price.0, 2 * PRICE);
+    }
+
+    #[test]
+    fn withdraw_near() {
+        let mut contract = new_contract();
+        testing_env!(context(&owner())
+            .account_balance(1000 * ONE_NEAR + 5 * ONE_NEAR)
+            .attached_deposit(1)
+            .build());
+        contract.admin_withdraw_near(U128(5 * ONE_NEAR), bob());
+        assert_eq!(transfers(), vec![(bob(), 5 * ONE_NEAR)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "not reserved for storage")]
+    fn withdraw_near_storage_reserve() {
+        let mut contract = new_contract();
+        let balance = 10 * ONE_NEAR;
+        testing_env!(context(&owner())
+            .account_balance(balance)
+            .attached_deposit(1)
+            .build());
+        contract.admin_withdraw_near(U128(balance), bob());
+    }
+
+    /// Balance of the contract which isn't reserved for its own storage
+    fn balance_above_storage(balance: Balance) -> Balance {
+        balance - env::storage_byte_cost() * env::storage_usage() as Balance
+    }
+
+    #[test]
+    #[should_panic(expected = "not reserved for storage and users")]
+    fn withdraw_near_prepaid_storage() {
+        let mut contract = new_contract();
+        set_context(&owner(), 10 * ONE_NEAR);
+        contract.admin_add_whitelist_with_storage(vec![alice()], 2);
+        assert_eq!(contract.storage_deposits_total, 10 * ONE_NEAR);
+        let balance = 1000 * ONE_NEAR;
+        testing_env!(context(&owner())
+            .account_balance(balance)
+            .attached_deposit(1)
+            .build());
+        contract.admin_withdraw_near(U128(balance_above_storage(balance)), bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "not reserved for storage and users")]
+    fn withdraw_near_pending_linkdrops() {
+        let mut contract = new_contract();
+        contract.pending_tokens = 1;
+        let balance = 1000 * ONE_NEAR;
+        testing_env!(context(&owner())
+            .account_balance(balance)
+            .attached_deposit(1)
+            .build());
+        contract.admin_withdraw_near(U128(balance_above_storage(balance)), bob());
+    }
+
+    #[test]
+    fn withdraw_ft() {
+        let mut contract = new_contract();
+        let token = AccountId::new_unchecked("stray.near".to_string());
+        contract.admin_withdraw_ft(token.clone(), U128(100), bob());
+        assert_eq!(function_calls(), vec![(token, "ft_transfer".to_string())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cheddar is held for depositors")]
+    fn withdraw_ft_cheddar() {
+        let mut contract = new_contract();
+        contract.admin_withdraw_ft(cheddar(), U128(100), bob());
+    }
+
+    #[test]
+    fn set_pending_tokens() {
+        let mut contract = new_contract();
+        contract.pending_tokens = 3;
+        assert_eq!(contract.tokens_left(), SIZE - 3);
+        contract.admin_set_pending_tokens(1);
+        assert_eq!(contract.tokens_left(), SIZE - 1);
+        assert_eq!(get_logs(), vec!["Pending tokens changed from 3 to 1"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only 20 tokens are left in the raffle")]
+    fn set_pending_tokens_above_raffle() {
+        let mut contract = new_contract();
+        contract.admin_set_pending_tokens(SIZE + 1);
+    }
+
+    #[test]
+    fn refund_all_token_deposits() {
+        let mut contract = new_contract();
+        contract.cheddar_deposits.insert(&alice(), &(10 * ONE_NEAR));
+        contract.cheddar_deposits.insert(&bob(), &ONE_NEAR);
+        assert_eq!(
+            contract.admin_refund_all_token_deposits(cheddar(), Some(1)),
+            1
+        );
+        assert_eq!(contract.admin_refund_all_token_deposits(cheddar(), None), 0);
+        assert_eq!(contract.balance_of(&alice()).0, 0);
+        assert_eq!(contract.balance_of(&bob()).0, 0);
+        let ft_transfers = function_calls()
+            .into_iter()
+            .filter(|call| *call == (cheddar(), "ft_transfer".to_string()))
+            .count();
+        assert_eq!(ft_transfers, 2);
+    }
+
+    #[test]
+    fn refund_zero_token_deposits() {
+        let mut contract = new_contract();
+        contract.cheddar_deposits.insert(&alice(), &0);
+        contract.cheddar_deposits.insert(&bob(), &ONE_NEAR);
+        assert_eq!(contract.admin_refund_all_token_deposits(cheddar(), None), 0);
+        assert!(contract.cheddar_deposits.get(&alice()).is_none());
+        let ft_transfers = function_calls()
+            .into_iter()
+            .filter(|call| *call == (cheddar(), "ft_transfer".to_string()))
+            .count();
+        assert_eq!(ft_transfers, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only CHEDDAR deposits are held")]
+    fn refund_all_other_token_deposits() {
+        let mut contract = new_contract();
+        contract.admin_refund_all_token_deposits(alice(), None);
+    }
+
+    /// Contract with bob as admin only allowed to manage the whitelist, signed by bob
+    fn whitelist_admin_contract() -> Contract {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), Some(Permission::MANAGE_WHITELIST));
+        set_context(&bob(), 1);
+        contract
+    }
+
+    #[test]
+    fn admin_permissions() {
+        let mut contract = whitelist_admin_contract();
+        assert_eq!(
+            contract.admin_permissions(bob()),
+            Permission::MANAGE_WHITELIST
+        );
+        assert_eq!(contract.admin_permissions(owner()), Permission::ALL);
+        assert_eq!(contract.admin_permissions(alice()), 0);
+        contract.admin_add_whitelist(vec![alice()], 2);
+        assert!(contract.whitelisted(&alice()));
+        contract.add_admin(alice(), Some(Permission::MANAGE_WHITELIST));
+        assert_eq!(
+            contract.admin_permissions(alice()),
+            Permission::MANAGE_WHITELIST
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Admin doesn't have the permission to call this method")]
+    fn admin_without_permission() {
+        let mut contract = whitelist_admin_contract();
+        contract.admin_set_sale_price(U128(2 * PRICE));
+    }
+
+    #[test]
+    #[should_panic(expected = "Admin doesn't have the permission to call this method")]
+    fn add_whitelist_accounts_without_permission() {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), Some(Permission::MANAGE_SALE));
+        set_context(&bob(), 1);
+        contract.add_whitelist_accounts(vec![alice()], Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method is private to owner or admin")]
+    fn add_whitelist_accounts_not_admin() {
+        let mut contract = new_contract();
+        set_context(&alice(), 1);
+        contract.add_whitelist_accounts(vec![alice()], Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can change the permissions of an admin")]
+    fn admin_changes_admin_permissions() {
+        let mut contract = new_contract();
+        contract.add_admin(alice(), None);
+        contract.add_admin(bob(), None);
+        set_context(&bob(), 1);
+        contract.add_admin(alice(), Some(Permission::MANAGE_TOKENS));
+    }
+
+    #[test]
+    fn owner_changes_admin_permissions() {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), None);
+        contract.add_admin(bob(), Some(Permission::MANAGE_TOKENS));
+        assert_eq!(contract.admin_permissions(bob()), Permission::MANAGE_TOKENS);
+        contract.remove_admin(bob());
+        assert_eq!(contract.admin_permissions(bob()), 0);
+        assert!(!contract.admins().contains(&bob()));
+    }
+
+    #[test]
+    #[should_panic(expected = "An admin needs at least one permission")]
+    fn admin_without_permissions() {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Admin doesn't have the permission to call this method")]
+    fn admin_grants_missing_permission() {
+        let mut contract = whitelist_admin_contract();
+        contract.add_admin(alice(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn admin_without_one_yocto() {
+        let mut contract = new_contract();
+        set_context(&owner(), 0);
+        contract.admin_set_sale_price(U128(2 * PRICE));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn add_whitelist_accounts_without_one_yocto() {
+        let mut contract = new_contract();
+        set_context(&owner(), 0);
+        contract.add_whitelist_accounts(vec![alice()], Some(2));
+    }
+
+    /// Royalties splitting the primary sale between bob and the given charity
+    fn charity_royalties(charity: AccountId) -> Royalties {
+        Royalties {
+            accounts: HashMap::from([(bob(), 9_000), (charity, 1_000)]),
+            percent: 10_000,
+            allow_zero_owner_remainder: true,
+        }
+    }
+
+    #[test]
+    fn set_royalties() {
+        let mut contract = new_contract();
+        let charity = AccountId::new_unchecked("charity.near".to_string());
+        contract.admin_set_royalties(charity_royalties(charity.clone()));
+        let royalties = contract.sale.initial_royalties.as_ref().unwrap();
+        assert_eq!(royalties.accounts.get(&charity), Some(&1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalties are frozen")]
+    fn set_royalties_frozen() {
+        let mut contract = new_contract();
+        contract.admin_freeze_royalties();
+        contract.admin_set_royalties(charity_royalties(alice()));
+    }
 }
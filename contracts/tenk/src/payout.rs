@@ -27,19 +27,65 @@ pub struct Payout {
     with_cheddar: bool,
 }
 
+/// Smallest NEAR transfer sent out, about the cost of creating an account.
+/// A smaller transfer to an unregistered account fails and the funds get stranded.
+const MIN_NEAR_TRANSFER: Balance = 1_820_000_000_000_000_000_000;
+
 impl Payout {
-    pub fn send_funds(self, cheddar_deposits: &mut LookupMap<AccountId, u128>) {
+    /// Payout of `amount` to a single account
+    pub(crate) fn to_account(account_id: AccountId, amount: Balance, with_cheddar: bool) -> Self {
+        Payout {
+            payout: HashMap::from([(account_id, amount.into())]),
+            with_cheddar,
+        }
+    }
+
+    pub fn send_funds(mut self, cheddar_deposits: &mut Deposits) {
         if self.with_cheddar {
-            self.payout.into_iter().for_each(|(account, amount)| {
-                let a = cheddar_deposits.get(&account).unwrap_or_default() + amount.0;
-                cheddar_deposits.insert(&account, &a);
-            });
+            self.payout
+                .into_iter()
+                // a zero balance would list the account as a depositor with nothing to refund
+                .filter(|(_, amount)| amount.0 > 0)
+                .for_each(|(account, amount)| {
+                    let a = cheddar_deposits.get(&account).unwrap_or_default() + amount.0;
+                    cheddar_deposits.insert(&account, &a);
+                });
         } else {
+            self.merge_small_shares();
             self.payout.into_iter().for_each(|(account, amount)| {
                 Promise::new(account).transfer(amount.0);
             });
         }
     }
+
+    /// Roll the shares below `MIN_NEAR_TRANSFER` into the largest payee
+    fn merge_small_shares(&mut self) {
+        let largest = match self
+            .payout
+            .iter()
+            .max_by(|(a, x), (b, y)| x.0.cmp(&y.0).then_with(|| b.cmp(a)))
+        {
+            Some((account, _)) => account.clone(),
+            None => return,
+        };
+        let mut dust = 0;
+        self.payout.retain(|account, amount| {
+            if *account != largest && amount.0 < MIN_NEAR_TRANSFER {
+                dust += amount.0;
+                return false;
+            }
+            true
+        });
+        if dust > 0 {
+            log!(
+                "Adding {} of payouts too small to send to {}",
+                dust,
+                largest
+            );
+            let amount = self.payout[&largest].0 + dust;
+            self.payout.insert(largest, amount.into());
+        }
+    }
 }
 
 pub trait Payouts {
@@ -63,16 +109,27 @@ pub trait Payouts {
 
 #[near_bindgen]
 impl Payouts for Contract {
-    #[allow(unused_variables)]
     fn nft_payout(&self, token_id: String, balance: U128, max_len_payout: Option<u32>) -> Payout {
         let owner_id = self
             .tokens
             .owner_by_id
             .get(&token_id)
             .expect("No such token_id");
-        self.sale.royalties.as_ref().map_or(Payout::default(), |r| {
-            r.create_payout(balance.0, &owner_id, false)
-        })
+        let token_royalties = self.token_royalties.get(&token_id);
+        let payout = self.royalties_of(&token_royalties).map_or_else(
+            || Payout {
+                payout: HashMap::from([(owner_id.clone(), balance)]),
+                with_cheddar: false,
+            },
+            |r| r.create_payout(balance.0, &owner_id, false),
+        );
+        if let Some(max_len_payout) = max_len_payout {
+            require!(
+                payout.payout.len() <= max_len_payout as usize,
+                format!("Payout has more than {} payees", max_len_payout)
+            );
+        }
+        payout
     }
 
     #[payable]
@@ -92,6 +149,32 @@ impl Payouts for Contract {
     }
 }
 
+#[near_bindgen]
+impl Contract {
+    /// Royalties of the token in basis points of the sale price, per account. The token's own
+    /// royalties if it has some, else the collection's. None if neither is set.
+    pub fn token_royalties(&self, token_id: TokenId) -> Option<HashMap<AccountId, BasisPoint>> {
+        let token_royalties = self.token_royalties.get(&token_id);
+        self.royalties_of(&token_royalties).map(|royalties| {
+            royalties
+                .accounts
+                .iter()
+                .map(|(account, share)| {
+                    let bps = *share as u32 * royalties.percent as u32 / 10_000;
+                    (account.clone(), bps as BasisPoint)
+                })
+                .collect()
+        })
+    }
+}
+
+impl Contract {
+    /// Royalties applying to a token with `token_royalties`, falling back to the collection's
+    fn royalties_of<'a>(&'a self, token_royalties: &'a Option<Royalties>) -> Option<&'a Royalties> {
+        token_royalties.as_ref().or(self.sale.royalties.as_ref())
+    }
+}
+
 #[near_sdk::witgen]
 type BasisPoint = u16;
 
@@ -103,6 +186,9 @@ const ONE_HUNDRED_PERCENT_IN_BPS: BasisPoint = 10_000;
 pub struct Royalties {
     pub accounts: HashMap<AccountId, BasisPoint>,
     pub percent: BasisPoint,
+    /// Allow `percent` to be 100%, leaving nothing to the owner outside of `accounts`
+    #[serde(default)]
+    pub allow_zero_owner_remainder: bool,
 }
 
 impl Royalties {
@@ -126,6 +212,10 @@ impl Royalties {
         require!(
             total == ONE_HUNDRED_PERCENT_IN_BPS,
             "total percent of each royalty split must equal 10,000"
+        );
+        require!(
+            self.allow_zero_owner_remainder || self.percent < ONE_HUNDRED_PERCENT_IN_BPS,
+            "royalties must leave a positive remainder to the owner, unless allow_zero_owner_remainder is set"
         )
     }
     pub(crate) fn create_payout(
@@ -148,6 +238,12 @@ impl Royalties {
                 .collect(),
             with_cheddar,
         };
+        // rounding dust of the split goes to the first payee, ordered by account id
+        let paid: u128 = payout.payout.values().map(|amount| amount.0).sum();
+        if let Some(first) = self.accounts.keys().min() {
+            let amount = payout.payout[first].0 + royalty_payment - paid;
+            payout.payout.insert(first.clone(), amount.into());
+        }
         let rest = balance - u128::min(royalty_payment, balance);
         let owner_payout: u128 = payout.payout.get(owner_id).map_or(0, |x| x.0) + rest;
         payout.payout.insert(owner_id.clone(), owner_payout.into());
@@ -159,7 +255,7 @@ impl Royalties {
         balance: Balance,
         owner_id: &AccountId,
         with_cheddar: bool,
-        cheddar_deposits: &mut LookupMap<AccountId, u128>,
+        cheddar_deposits: &mut Deposits,
     ) {
         self.create_payout(balance, owner_id, with_cheddar)
             .send_funds(cheddar_deposits);
@@ -169,3 +265,189 @@ impl Royalties {
 fn apply_percent(percent: BasisPoint, int: u128) -> u128 {
     int * percent as u128 / 10_000u128
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn royalties(percent: BasisPoint, allow_zero_owner_remainder: bool) -> Royalties {
+        let mut accounts = HashMap::new();
+        accounts.insert(AccountId::new_unchecked("bob.near".to_string()), 6_000);
+        accounts.insert(AccountId::new_unchecked("eve.near".to_string()), 4_000);
+        Royalties {
+            accounts,
+            percent,
+            allow_zero_owner_remainder,
+        }
+    }
+
+    fn split(shares: &[(&str, BasisPoint)], percent: BasisPoint) -> Royalties {
+        Royalties {
+            accounts: shares
+                .iter()
+                .map(|(account, share)| (AccountId::new_unchecked(account.to_string()), *share))
+                .collect(),
+            percent,
+            allow_zero_owner_remainder: true,
+        }
+    }
+
+    fn amounts(payout: &Payout) -> Vec<(String, u128)> {
+        let mut amounts: Vec<(String, u128)> = payout
+            .payout
+            .iter()
+            .map(|(account, amount)| (account.to_string(), amount.0))
+            .collect();
+        amounts.sort();
+        amounts
+    }
+
+    #[test]
+    fn two_way_split() {
+        let royalties = split(&[("bob.near", 7_500), ("eve.near", 2_500)], 10_000);
+        royalties.validate();
+        let payout = royalties.create_payout(1_000, &"owner.near".parse().unwrap(), false);
+        assert_eq!(
+            amounts(&payout),
+            vec![
+                ("bob.near".to_string(), 750),
+                ("eve.near".to_string(), 250),
+                ("owner.near".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn three_way_split_with_dust() {
+        let royalties = split(
+            &[
+                ("bob.near", 3_333),
+                ("carol.near", 3_333),
+                ("eve.near", 3_334),
+            ],
+            10_000,
+        );
+        royalties.validate();
+        let payout = royalties.create_payout(100, &"owner.near".parse().unwrap(), false);
+        // 33 + 33 + 33 leaves 1 yocto of dust for the first payee
+        assert_eq!(
+            amounts(&payout),
+            vec![
+                ("bob.near".to_string(), 34),
+                ("carol.near".to_string(), 33),
+                ("eve.near".to_string(), 33),
+                ("owner.near".to_string(), 0)
+            ]
+        );
+        let total: u128 = amounts(&payout).iter().map(|(_, amount)| amount).sum();
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn split_with_owner_remainder() {
+        let royalties = split(&[("bob.near", 5_000), ("eve.near", 5_000)], 1_000);
+        let payout = royalties.create_payout(1_001, &"owner.near".parse().unwrap(), false);
+        let total: u128 = amounts(&payout).iter().map(|(_, amount)| amount).sum();
+        assert_eq!(total, 1_001);
+        assert_eq!(amounts(&payout)[2], ("owner.near".to_string(), 901));
+    }
+
+    #[test]
+    fn cheddar_split_skips_zero_shares() {
+        let royalties = split(&[("bob.near", 7_500), ("eve.near", 2_500)], 10_000);
+        let mut contract = new_contract();
+        royalties.send_funds(1_000, &owner(), true, &mut contract.cheddar_deposits);
+        assert_eq!(contract.balance_of(&bob()).0, 750);
+        assert_eq!(contract.cheddar_deposits.get(&owner()), None);
+        assert_eq!(contract.cheddar_deposits.num_depositors(), 2);
+    }
+
+    fn contract_with_royalties(royalties: Option<Royalties>) -> Contract {
+        let mut sale = open_sale();
+        sale.royalties = royalties;
+        let mut contract = new_contract_with_sale(sale);
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract
+    }
+
+    fn minted_token(contract: &Contract) -> String {
+        contract
+            .nft_tokens_for_owner(alice(), None, None)
+            .remove(0)
+            .token_id
+    }
+
+    #[test]
+    fn nft_payout_sums_to_balance() {
+        let contract = contract_with_royalties(Some(royalties(1_000, false)));
+        let token_id = minted_token(&contract);
+        let payout = contract.nft_payout(token_id, U128(1_000_003), Some(10));
+        assert_eq!(payout.payout.len(), 3);
+        assert_eq!(payout.payout[&alice()].0, 900_003);
+        let total: u128 = amounts(&payout).iter().map(|(_, amount)| amount).sum();
+        assert_eq!(total, 1_000_003);
+    }
+
+    #[test]
+    fn nft_payout_without_royalties() {
+        let contract = contract_with_royalties(None);
+        let token_id = minted_token(&contract);
+        let payout = contract.nft_payout(token_id, U128(1_000), None);
+        assert_eq!(amounts(&payout), vec![("alice.near".to_string(), 1_000)]);
+    }
+
+    #[test]
+    fn token_royalties_override() {
+        let mut contract = contract_with_royalties(Some(royalties(1_000, false)));
+        let token_id = minted_token(&contract);
+        let default = contract.token_royalties(token_id.clone()).unwrap();
+        assert_eq!(default[&bob()], 600);
+
+        contract
+            .admin_set_token_royalties(token_id.clone(), Some(split(&[("bob.near", 10_000)], 500)));
+        let overridden = contract.token_royalties(token_id.clone()).unwrap();
+        assert_eq!(overridden, HashMap::from([(bob(), 500)]));
+        let payout = contract.nft_payout(token_id.clone(), U128(1_000), None);
+        assert_eq!(
+            amounts(&payout),
+            vec![
+                ("alice.near".to_string(), 950),
+                ("bob.near".to_string(), 50)
+            ]
+        );
+
+        contract.admin_set_token_royalties(token_id.clone(), None);
+        assert_eq!(contract.token_royalties(token_id), Some(default));
+    }
+
+    #[test]
+    #[should_panic(expected = "Payout has more than 2 payees")]
+    fn nft_payout_max_len() {
+        let contract = contract_with_royalties(Some(royalties(1_000, false)));
+        let token_id = minted_token(&contract);
+        contract.nft_payout(token_id, U128(1_000), Some(2));
+    }
+
+    #[test]
+    fn small_shares_merged_into_largest() {
+        let royalties = split(&[("bob.near", 9_990), ("eve.near", 10)], 10_000);
+        let mut payout = royalties.create_payout(ONE_NEAR, &"owner.near".parse().unwrap(), false);
+        payout.merge_small_shares();
+        // eve's 0.001 NEAR is below the transfer minimum, owner's share is 0
+        assert_eq!(amounts(&payout), vec![("bob.near".to_string(), ONE_NEAR)]);
+    }
+
+    #[test]
+    fn owner_remainder() {
+        royalties(9_999, false).validate();
+        royalties(10_000, true).validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "royalties must leave a positive remainder to the owner")]
+    fn zero_owner_remainder_rejected() {
+        royalties(10_000, false).validate();
+    }
+}
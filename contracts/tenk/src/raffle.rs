@@ -14,7 +14,9 @@ pub(crate) fn append_slice(id: &[u8], extra: &[u8]) -> Vec<u8> {
     [id, extra].concat()
 }
 
-/// This is similar to the raffle collection but doesn't keep track of past winners
+/// This is similar to the raffle collection but doesn't keep track of past winners.
+/// Values which were moved away from their own index also store their index, so that
+/// a specific value can be taken out of the raffle.
 #[derive(BorshSerialize, BorshDeserialize)]
 #[cfg_attr(not(feature = "expensive-debug"), derive(Debug))]
 pub struct Raffle {
@@ -51,6 +53,10 @@ impl Raffle {
         append_slice(&self.prefix, &index.to_le_bytes()[..])
     }
 
+    fn value_to_index_key(&self, raw_value: &[u8]) -> Vec<u8> {
+        [&self.prefix[..], b"i", raw_value].concat()
+    }
+
     /// # Panics
     ///
     /// Panics if `index` is out of bounds.
@@ -64,8 +70,14 @@ impl Raffle {
             let raw_last_value = self
                 .pop_raw()
                 .expect("checked `index < len` above, so `len > 0`");
+            env::storage_write(
+                &self.value_to_index_key(&raw_last_value),
+                &index.to_le_bytes(),
+            );
             if env::storage_write(&lookup_key, &raw_last_value) {
-                expect_consistent_state(env::storage_get_evicted())
+                let raw_value = expect_consistent_state(env::storage_get_evicted());
+                env::storage_remove(&self.value_to_index_key(&raw_value));
+                raw_value
             } else {
                 // no value was at location its index is the value
                 index.to_le_bytes().to_vec()
@@ -82,7 +94,9 @@ impl Raffle {
             let last_lookup_key = self.index_to_lookup_key(self.len);
             let raw_last_value = if env::storage_remove(&last_lookup_key) {
                 // if key is en in storage, it's value will be stored as last evicted value
-                expect_consistent_state(env::storage_get_evicted())
+                let raw_value = expect_consistent_state(env::storage_get_evicted());
+                env::storage_remove(&self.value_to_index_key(&raw_value));
+                raw_value
             } else {
                 // otherwise the value is index of the last element
                 self.len.to_le_bytes().to_vec()
@@ -91,10 +105,91 @@ impl Raffle {
         }
     }
 
-    pub fn draw(&mut self) -> u64 {
-        let seed_num = crate::util::get_random_number(0) as u64;
+    /// Put a previously drawn `value` back so it can be drawn again.
+    pub fn push(&mut self, value: u64) {
+        let index = self.len;
+        self.len += 1;
+        // a missing value means the value is its index
+        if value != index {
+            env::storage_write(&self.index_to_lookup_key(index), &value.to_le_bytes());
+            env::storage_write(
+                &self.value_to_index_key(&value.to_le_bytes()),
+                &index.to_le_bytes(),
+            );
+        }
+    }
+
+    /// Append the `n` values following the current length. Only valid while nothing was drawn or
+    /// taken, otherwise values at or above the length may already be in the raffle.
+    pub fn extend(&mut self, n: u64) {
+        // values which were never moved are at their own index, so there is nothing to write
+        self.len += n;
+    }
+
+    /// Remove `value` so it can't be drawn anymore. Returns `false` if it was already drawn.
+    pub fn take(&mut self, value: u64) -> bool {
+        let index = match env::storage_read(&self.value_to_index_key(&value.to_le_bytes())) {
+            Some(raw_index) => u64::try_from_slice(&raw_index).unwrap(),
+            // a value which was never moved is still at its own index
+            None if value < self.len && !env::storage_has_key(&self.index_to_lookup_key(value)) => {
+                value
+            }
+            None => return false,
+        };
+        self.swap_remove_raw(index);
+        true
+    }
+
+    /// Store the index of the moved values at indexes `[from, from + limit)`, for raffles written
+    /// before values kept their index. Returns the index to continue from, `len` once done.
+    /// Draws in between are fine: values they move get their index written anyway.
+    pub fn index_moved_values(&mut self, from: u64, limit: u64) -> u64 {
+        let end = u64::min(from.saturating_add(limit), self.len);
+        for index in from..end {
+            if let Some(raw_value) = env::storage_read(&self.index_to_lookup_key(index)) {
+                env::storage_write(&self.value_to_index_key(&raw_value), &index.to_le_bytes());
+            }
+        }
+        u64::max(from, end)
+    }
+
+    /// Value at `index` without removing it, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: u64) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        Some(
+            env::storage_read(&self.index_to_lookup_key(index))
+                .map_or(index, |raw_value| u64::try_from_slice(&raw_value).unwrap()),
+        )
+    }
+
+    /// Remove and return a random value. With a `seed` the draws are reproducible on testnet,
+    /// otherwise they use the block's random seed.
+    pub fn draw(&mut self, seed: Option<&[u8]>) -> u64 {
+        let seed_num = match seed {
+            #[cfg(feature = "testnet")]
+            Some(seed) => crate::util::get_seeded_random_number(seed, self.len),
+            _ => crate::util::get_random_number(0),
+        } as u64;
         u64::try_from_slice(&self.swap_remove_raw(seed_num % self.len())).unwrap()
     }
+
+    /// Remove and return `n` random values, or all of them if fewer are left. Draws the same
+    /// values as `n` calls to `draw`, reading the block's random seed only once.
+    pub fn draw_many(&mut self, n: u64, seed: Option<&[u8]>) -> Vec<u64> {
+        let random_number = crate::util::get_random_number(0) as u64;
+        (0..u64::min(n, self.len))
+            .map(|_| {
+                let seed_num = match seed {
+                    #[cfg(feature = "testnet")]
+                    Some(seed) => crate::util::get_seeded_random_number(seed, self.len) as u64,
+                    _ => random_number,
+                };
+                u64::try_from_slice(&self.swap_remove_raw(seed_num % self.len)).unwrap()
+            })
+            .collect()
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -105,7 +200,7 @@ mod tests {
 
     use super::Raffle;
     use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::testing_env;
+    use near_sdk::{env, testing_env};
 
     #[test]
     pub fn test_swap_remove() {
@@ -116,9 +211,111 @@ mod tests {
         testing_env!(context.build());
         for _ in 0..100 {
             let len = vec.len();
-            assert!(set.insert(vec.draw()));
+            assert!(set.insert(vec.draw(None)));
             assert_eq!(len - 1, vec.len());
             testing_env!(context.random_seed(rng.gen()).build());
         }
     }
+
+    #[test]
+    pub fn test_take() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(3);
+        let mut vec = Raffle::new(b"v".to_vec(), 100);
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.build());
+        let mut drawn: HashSet<u64> = HashSet::new();
+        for i in 0..30 {
+            assert!(drawn.insert(vec.draw(None)));
+            testing_env!(context.random_seed(rng.gen()).build());
+            // take values which are likely to have been moved by the draws
+            let value = 99 - i;
+            assert_eq!(vec.take(value), drawn.insert(value));
+        }
+        assert!(drawn.iter().all(|value| !vec.take(*value)));
+        while !vec.is_empty() {
+            assert!(drawn.insert(vec.draw(None)));
+            testing_env!(context.random_seed(rng.gen()).build());
+        }
+        assert_eq!(drawn.len(), 100);
+        assert!(!vec.take(5));
+    }
+
+    #[test]
+    pub fn test_index_moved_values() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(4);
+        let mut vec = Raffle::new(b"v".to_vec(), 100);
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.build());
+        let mut drawn: HashSet<u64> = HashSet::new();
+        for _ in 0..50 {
+            assert!(drawn.insert(vec.draw(None)));
+            testing_env!(context.random_seed(rng.gen()).build());
+        }
+        // drop the index of moved values, as written before it existed
+        let moved: Vec<u64> = (0..vec.len())
+            .filter_map(|index| vec.get(index).filter(|value| *value != index))
+            .collect();
+        assert!(!moved.is_empty());
+        for value in 0..100u64 {
+            env::storage_remove(&vec.value_to_index_key(&value.to_le_bytes()));
+        }
+        assert!(!vec.take(moved[0]));
+
+        assert_eq!(vec.index_moved_values(0, 30), 30);
+        assert!(drawn.insert(vec.draw(None)));
+        assert_eq!(vec.index_moved_values(30, 30), vec.len());
+        assert_eq!(vec.index_moved_values(vec.len(), 30), vec.len());
+        for value in moved {
+            assert_eq!(vec.take(value), drawn.insert(value));
+        }
+        while !vec.is_empty() {
+            assert!(drawn.insert(vec.draw(None)));
+            testing_env!(context.random_seed(rng.gen()).build());
+        }
+        assert_eq!(drawn.len(), 100);
+    }
+
+    #[test]
+    pub fn test_draw_many() {
+        let mut vec = Raffle::new(b"v".to_vec(), 20);
+        let mut other = Raffle::new(b"o".to_vec(), 20);
+        testing_env!(VMContextBuilder::new().random_seed([7; 32]).build());
+        let drawn = vec.draw_many(10, None);
+        let expected: Vec<u64> = (0..10).map(|_| other.draw(None)).collect();
+        assert_eq!(drawn, expected);
+        assert_eq!(vec.len(), 10);
+
+        let seeded = vec.draw_many(15, Some(b"seed"));
+        assert_eq!(seeded.len(), 10);
+        assert!(vec.is_empty());
+        let all: HashSet<u64> = drawn.into_iter().chain(seeded).collect();
+        assert_eq!(all.len(), 20);
+    }
+
+    #[test]
+    pub fn test_extend() {
+        let mut vec = Raffle::new(b"v".to_vec(), 10);
+        testing_env!(VMContextBuilder::new().build());
+        vec.extend(5);
+        assert_eq!(vec.len(), 15);
+        let mut drawn: Vec<u64> = (0..15).map(|_| vec.draw(None)).collect();
+        drawn.sort_unstable();
+        assert_eq!(drawn, (0..15).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    pub fn test_push() {
+        let mut vec = Raffle::new(b"v".to_vec(), 10);
+        testing_env!(VMContextBuilder::new().build());
+        let drawn: Vec<u64> = (0..10).map(|_| vec.draw(None)).collect();
+        assert!(vec.is_empty());
+        vec.push(drawn[2]);
+        vec.push(drawn[7]);
+        assert_eq!(vec.len(), 2);
+        let mut redrawn = vec![vec.draw(None), vec.draw(None)];
+        redrawn.sort_unstable();
+        let mut expected = vec![drawn[2], drawn[7]];
+        expected.sort_unstable();
+        assert_eq!(redrawn, expected);
+    }
 }
@@ -37,8 +37,15 @@ impl NonFungibleTokenCore for Contract {
         approval_id: Option<u64>,
         memo: Option<String>,
     ) {
-        self.tokens
-            .nft_transfer(receiver_id, token_id, approval_id, memo)
+        self.assert_not_frozen();
+        self.assert_transfers_unlocked();
+        self.assert_transferable(&token_id);
+        let accounts = self.transfer_parties(&token_id, &receiver_id);
+        self.track_holders(&accounts, |contract| {
+            contract
+                .tokens
+                .nft_transfer(receiver_id, token_id, approval_id, memo)
+        })
     }
 
     /// Transfer token and call a method on a receiver contract. A successful
@@ -83,13 +90,22 @@ impl NonFungibleTokenCore for Contract {
         memo: Option<String>,
         msg: String,
     ) -> PromiseOrValue<bool> {
-        self.tokens
-            .nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+        self.assert_not_frozen();
+        self.assert_transfers_unlocked();
+        self.assert_transferable(&token_id);
+        let accounts = self.transfer_parties(&token_id, &receiver_id);
+        self.track_holders(&accounts, |contract| {
+            contract
+                .tokens
+                .nft_transfer_call(receiver_id, token_id, approval_id, memo, msg)
+        })
     }
 
     /// Returns the token with the given `token_id` or `null` if no such token.
     fn nft_token(&self, token_id: TokenId) -> Option<Token> {
-        self.tokens.nft_token(token_id)
+        self.tokens
+            .nft_token(token_id)
+            .map(|token| self.with_placeholder(token))
     }
 }
 
@@ -103,12 +119,69 @@ impl NonFungibleTokenResolver for Contract {
         token_id: TokenId,
         approved_account_ids: Option<std::collections::HashMap<AccountId, u64>>,
     ) -> bool {
-        self.tokens.nft_resolve_transfer(
-            previous_owner_id,
-            receiver_id,
-            token_id,
-            approved_account_ids,
-        )
+        let accounts = [previous_owner_id.clone(), receiver_id.clone()];
+        self.track_holders(&accounts, |contract| {
+            contract.tokens.nft_resolve_transfer(
+                previous_owner_id,
+                receiver_id,
+                token_id,
+                approved_account_ids,
+            )
+        })
+    }
+}
+
+impl Contract {
+    /// Current owner of `token_id`, if any, and `receiver_id`
+    fn transfer_parties(&self, token_id: &TokenId, receiver_id: &AccountId) -> Vec<AccountId> {
+        self.tokens
+            .owner_by_id
+            .get(token_id)
+            .into_iter()
+            .chain(std::iter::once(receiver_id.clone()))
+            .collect()
+    }
+
+    /// Run `f`, keeping `num_holders` up to date with the accounts which got their first token
+    /// or gave away their last one
+    pub(crate) fn track_holders<T>(
+        &mut self,
+        accounts: &[AccountId],
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let held: Vec<bool> = accounts.iter().map(|a| self.holds_tokens(a)).collect();
+        let result = f(self);
+        for (account_id, held) in accounts.iter().zip(held) {
+            let holds = self.holds_tokens(account_id);
+            if !self.counts_holder(account_id) {
+                // counted now, `admin_count_holders` may already be past its tokens
+                if holds {
+                    self.counted_holders.insert(account_id);
+                    self.num_holders += 1;
+                }
+                continue;
+            }
+            match (held, holds) {
+                (false, true) => self.num_holders += 1,
+                (true, false) => self.num_holders -= 1,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Whether `num_holders` counts `account_id` if it holds tokens. Only accounts not yet
+    /// gone over aren't while `admin_count_holders` is counting.
+    pub(crate) fn counts_holder(&self, account_id: &AccountId) -> bool {
+        !matches!(self.holders_count, Some(HoldersCount::Counting(_)))
+            || self.counted_holders.contains(account_id)
+    }
+
+    fn holds_tokens(&self, account_id: &AccountId) -> bool {
+        match &self.tokens.tokens_per_owner {
+            Some(tokens_per_owner) => tokens_per_owner.contains_key(account_id),
+            None => false,
+        }
     }
 }
 
@@ -143,7 +216,23 @@ impl NonFungibleTokenApproval for Contract {
         account_id: AccountId,
         msg: Option<String>,
     ) -> Option<Promise> {
-        self.tokens.nft_approve(token_id, account_id, msg)
+        self.assert_not_frozen();
+        let promise = self
+            .tokens
+            .nft_approve(token_id.clone(), account_id.clone(), msg);
+        let approval_id = self
+            .tokens
+            .approvals_by_id
+            .as_ref()
+            .and_then(|approvals_by_id| approvals_by_id.get(&token_id))
+            .and_then(|approvals| approvals.get(&account_id).copied())
+            .unwrap_or_else(|| env::abort());
+        self.emit_event(NftApproved {
+            token_id: &token_id,
+            account_id: &account_id,
+            approval_id,
+        });
+        promise
     }
 
     /// Revoke an approved account for a specific token.
@@ -160,7 +249,12 @@ impl NonFungibleTokenApproval for Contract {
     /// * `account_id`: the account to remove from `approvals`
     #[payable]
     fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
-        self.tokens.nft_revoke(token_id, account_id)
+        self.tokens
+            .nft_revoke(token_id.clone(), account_id.clone());
+        self.emit_event(NftRevoked {
+            token_id: &token_id,
+            account_id: Some(&account_id),
+        });
     }
 
     /// Revoke all approved accounts for a specific token.
@@ -176,7 +270,11 @@ impl NonFungibleTokenApproval for Contract {
     /// * `token_id`: the token with approvals to revoke
     #[payable]
     fn nft_revoke_all(&mut self, token_id: TokenId) {
-        self.tokens.nft_revoke_all(token_id)
+        self.tokens.nft_revoke_all(token_id.clone());
+        self.emit_event(NftRevoked {
+            token_id: &token_id,
+            account_id: None,
+        });
     }
 
     /// Check if a token is approved for transfer by a given account, optionally
@@ -223,7 +321,11 @@ impl NonFungibleTokenEnumeration for Contract {
         from_index: Option<near_sdk::json_types::U128>,
         limit: Option<u64>,
     ) -> Vec<Token> {
-        self.tokens.nft_tokens(from_index, limit)
+        self.tokens
+            .nft_tokens(from_index, limit)
+            .into_iter()
+            .map(|token| self.with_placeholder(token))
+            .collect()
     }
 
     /// Get number of tokens owned by a given account
@@ -256,5 +358,196 @@ impl NonFungibleTokenEnumeration for Contract {
     ) -> Vec<Token> {
         self.tokens
             .nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .map(|token| self.with_placeholder(token))
+            .collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{test_utils::get_logs, testing_env};
+    use std::collections::HashMap;
+
+    #[test]
+    fn transfer_lock() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        contract.nft_mint_many_ungaurded(1, &owner(), true, false);
+        contract.admin_set_transfer_lock(Some(NOW + 1_000));
+
+        // the owner can still transfer, e.g. to airdrop
+        set_context(&owner(), 1);
+        let owner_token = contract.nft_tokens_for_owner(owner(), None, None)[0]
+            .token_id
+            .clone();
+        contract.nft_transfer(bob(), owner_token, None, None);
+
+        // unlocked once the lock time is reached
+        testing_env!(context(&alice())
+            .block_timestamp((NOW + 1_000) * 1_000_000)
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers locked")]
+    fn transfer_locked() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_set_transfer_lock(Some(NOW + 1));
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+    }
+
+    #[test]
+    fn freeze_all() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.nft_mint_many_ungaurded(1, &owner(), true, false);
+        contract.admin_freeze_all();
+        assert!(contract.is_frozen());
+
+        // the owner can still move tokens for recovery
+        set_context(&owner(), 1);
+        let owner_token = contract.nft_tokens_for_owner(owner(), None, None)[0]
+            .token_id
+            .clone();
+        contract.nft_transfer(bob(), owner_token, None, None);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 1);
+
+        contract.admin_unfreeze_all();
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is frozen")]
+    fn frozen_transfer() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_freeze_all();
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is frozen")]
+    fn frozen_approve() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_freeze_all();
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_approve(tokens[0].token_id.clone(), bob(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is soulbound and can't be transferred")]
+    fn soulbound() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        contract.admin_set_soulbound(tokens[0].token_id.clone(), true);
+        assert!(contract.is_soulbound(tokens[0].token_id.clone()));
+        assert!(!contract.is_soulbound(tokens[1].token_id.clone()));
+
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[1].token_id.clone(), None, None);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+    }
+
+    #[test]
+    fn soulbound_unset() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.admin_set_soulbound(tokens[0].token_id.clone(), true);
+        contract.admin_set_soulbound(tokens[0].token_id.clone(), false);
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 1);
+    }
+
+    #[test]
+    fn approve_and_revoke() {
+        let mut contract = new_contract();
+        let token_id = contract.nft_mint_many_ungaurded(1, &alice(), true, false)[0]
+            .token_id
+            .clone();
+
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_approve(token_id.clone(), bob(), None);
+        assert!(contract.nft_is_approved(token_id.clone(), bob(), Some(1)));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"nft_approve","data":[{{"token_id":"{}","account_id":"bob.near","approval_id":1}}]}}"#,
+                token_id
+            )]
+        );
+
+        set_context(&alice(), 1);
+        contract.nft_revoke(token_id.clone(), bob());
+        assert!(!contract.nft_is_approved(token_id.clone(), bob(), None));
+        assert!(get_logs()[0].contains(r#""event":"nft_revoke""#));
+
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_approve(token_id.clone(), bob(), None);
+        assert!(contract.nft_is_approved(token_id.clone(), bob(), Some(2)));
+        set_context(&alice(), 1);
+        contract.nft_revoke_all(token_id.clone());
+        assert!(!contract.nft_is_approved(token_id.clone(), bob(), None));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"nft_revoke","data":[{{"token_id":"{}","account_id":null}}]}}"#,
+                token_id
+            )]
+        );
+    }
+
+    #[test]
+    fn auto_approve() {
+        let mut contract = new_contract();
+        contract.admin_set_auto_approve_account(Some(bob()));
+        assert_eq!(contract.default_approved_marketplace(), Some(bob()));
+        set_context(&alice(), PRICE + ONE_NEAR);
+        let token = contract.nft_mint_one(false);
+        assert_eq!(
+            token.approved_account_ids,
+            Some(HashMap::from([(bob(), 1)]))
+        );
+        assert!(contract.nft_is_approved(token.token_id.clone(), bob(), Some(1)));
+
+        // approvals made later get the next id
+        set_context(&alice(), ONE_NEAR);
+        contract.nft_approve(token.token_id.clone(), cheddar(), None);
+        assert!(contract.nft_is_approved(token.token_id.clone(), cheddar(), Some(2)));
+    }
+
+    #[test]
+    fn num_holders() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        contract.nft_mint_many_ungaurded(1, &bob(), true, false);
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+        assert_eq!(contract.nft_supply_for_owner(bob()).0, 1);
+        assert_eq!(contract.num_holders(), Some(2));
+
+        set_context(&alice(), 1);
+        contract.nft_transfer(bob(), tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.num_holders(), Some(2));
+        contract.nft_transfer(bob(), tokens[1].token_id.clone(), None, None);
+        assert_eq!(contract.num_holders(), Some(1));
+
+        set_context(&bob(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert_eq!(contract.num_holders(), Some(1));
+        contract.nft_transfer(alice(), tokens[1].token_id.clone(), None, None);
+        assert_eq!(contract.num_holders(), Some(2));
     }
 }
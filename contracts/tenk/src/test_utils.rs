@@ -0,0 +1,138 @@
+use crate::*;
+use near_sdk::mock::VmAction;
+use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+use near_sdk::testing_env;
+use near_units::parse_near;
+
+pub const ONE_NEAR: u128 = parse_near!("1 N");
+pub const PRICE: u128 = parse_near!("10 N");
+pub const SIZE: u32 = 20;
+/// Block time used by `context`
+pub const NOW: TimestampMs = 1_650_000_000_000;
+
+pub fn tenk() -> AccountId {
+    AccountId::new_unchecked("tenk.near".to_string())
+}
+
+pub fn owner() -> AccountId {
+    AccountId::new_unchecked("root.near".to_string())
+}
+
+pub fn alice() -> AccountId {
+    AccountId::new_unchecked("alice.near".to_string())
+}
+
+pub fn bob() -> AccountId {
+    AccountId::new_unchecked("bob.near".to_string())
+}
+
+pub fn cheddar() -> AccountId {
+    AccountId::new_unchecked("cheddar.token".to_string())
+}
+
+/// Sale in presale since the epoch, with no public sale
+pub fn presale() -> Sale {
+    let mut sale = Sale::new(PRICE);
+    sale.presale_start = Some(0);
+    sale
+}
+
+/// Sale which is open to the public since the epoch
+pub fn open_sale() -> Sale {
+    let mut sale = Sale::new(PRICE);
+    sale.public_sale_start = Some(0);
+    sale
+}
+
+/// New contract with the owner as signer, attaching the 1 yoctoNEAR required by admin methods
+pub fn new_contract_with_sale(sale: Sale) -> Contract {
+    set_context(&owner(), 1);
+    Contract::new(
+        owner(),
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "name".to_string(),
+            symbol: "sym".to_string(),
+            icon: None,
+            base_uri: Some("https://".to_string()),
+            reference: None,
+            reference_hash: None,
+        },
+        SIZE,
+        sale,
+        cheddar(),
+        10_000,
+        10,
+    )
+}
+
+pub fn new_contract() -> Contract {
+    new_contract_with_sale(open_sale())
+}
+
+/// Metadata of a minted token as returned by `nft_token`
+pub fn token_metadata(contract: &Contract, token_id: &str) -> TokenMetadata {
+    contract
+        .nft_token(token_id.to_string())
+        .and_then(|token| token.metadata)
+        .unwrap()
+}
+
+/// Set signer and predecessor to `account_id` with the given attached deposit
+pub fn set_context(account_id: &AccountId, deposit: Balance) {
+    testing_env!(context(account_id).attached_deposit(deposit).build());
+}
+
+/// Set signer and predecessor to `account_id` at block time `time`
+pub fn set_time(account_id: &AccountId, time: TimestampMs) {
+    testing_env!(context(account_id)
+        .block_timestamp(time * 1_000_000)
+        .build());
+}
+
+pub fn context(account_id: &AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(tenk())
+        .signer_account_id(account_id.clone())
+        .predecessor_account_id(account_id.clone())
+        .block_timestamp(NOW * 1_000_000)
+        .account_balance(1000 * ONE_NEAR);
+    builder
+}
+
+/// NEAR transfers created so far, as `(receiver, amount)`
+pub fn transfers() -> Vec<(AccountId, Balance)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let receiver_id = receipt.receiver_id;
+            receipt
+                .actions
+                .into_iter()
+                .filter_map(move |action| match action {
+                    VmAction::Transfer { deposit } => Some((receiver_id.clone(), deposit)),
+                    _ => None,
+                })
+        })
+        .collect()
+}
+
+/// Function calls created so far, as `(receiver, method)`
+pub fn function_calls() -> Vec<(AccountId, String)> {
+    get_created_receipts()
+        .into_iter()
+        .flat_map(|receipt| {
+            let receiver_id = receipt.receiver_id;
+            receipt
+                .actions
+                .into_iter()
+                .filter_map(move |action| match action {
+                    VmAction::FunctionCall { function_name, .. } => {
+                        Some((receiver_id.clone(), function_name))
+                    }
+                    _ => None,
+                })
+        })
+        .collect()
+}
@@ -48,10 +48,29 @@ pub struct Sale {
     pub initial_royalties: Option<Royalties>,
     pub presale_start: Option<TimestampMs>,
     pub public_sale_start: Option<TimestampMs>,
+    /// After this time the sale has ended and nothing can be minted anymore
+    pub public_sale_end: Option<TimestampMs>,
     pub allowance: Option<u32>,
     pub presale_price: Option<U128>,
     pub price: U128,
     pub mint_rate_limit: Option<u32>,
+    /// When set, prices can't be changed once the public sale is open
+    #[serde(default)]
+    pub freeze_price_on_open: bool,
+    /// Discount in basis points for accounts already holding a token of the collection.
+    /// Applies on top of the current presale or public sale price.
+    pub holder_discount_bps: Option<u16>,
+    /// Mint to and charge the predecessor instead of the signer. See `Contract::minter_account`.
+    #[serde(default)]
+    pub use_predecessor: bool,
+    /// Max number of tokens which can be minted during the presale, the rest is kept for the public sale
+    pub presale_supply_cap: Option<u32>,
+    /// Share in basis points of the price paid to the referrer of a mint
+    pub referral_bps: Option<u16>,
+    /// Min time between two mints of an account, to slow down bots
+    pub mint_cooldown_ms: Option<u64>,
+    /// Reject mints attaching more than this many times the cost and storage, to catch typos
+    pub max_overpay_multiplier: Option<u32>,
 }
 
 impl Sale {
@@ -63,13 +82,62 @@ impl Sale {
             initial_royalties: Default::default(),
             presale_start: Default::default(),
             public_sale_start: Default::default(),
+            public_sale_end: None,
             allowance: Default::default(),
             presale_price: Default::default(),
             mint_rate_limit: Some(10),
+            freeze_price_on_open: false,
+            holder_discount_bps: None,
+            use_predecessor: false,
+            presale_supply_cap: None,
+            referral_bps: None,
+            mint_cooldown_ms: None,
+            max_overpay_multiplier: None,
         }
     }
 
     pub fn validate(&self) {
+        require!(self.price.0 > 0, "price must be positive");
+        if let Some(presale_price) = self.presale_price {
+            require!(presale_price.0 > 0, "presale price must be positive");
+            require!(
+                presale_price.0 <= self.price.0,
+                "presale price can't be higher than the public sale price"
+            );
+        }
+        if let Some(limit) = self.mint_rate_limit {
+            require!(limit > 0, "mint rate limit must be positive");
+        }
+        if let (Some(presale), Some(public)) = (self.presale_start, self.public_sale_start) {
+            require!(
+                presale <= public,
+                "presale must start before the public sale"
+            );
+        }
+        if let Some(end) = self.public_sale_end {
+            require!(
+                [self.presale_start, self.public_sale_start]
+                    .iter()
+                    .flatten()
+                    .all(|start| *start < end),
+                "sale must end after it starts"
+            );
+        }
+        if let Some(discount) = self.holder_discount_bps {
+            require!(
+                discount <= 10_000,
+                "holder discount is in basis points and can't be more than 10,000"
+            );
+        }
+        if let Some(referral) = self.referral_bps {
+            require!(
+                referral <= 10_000,
+                "referral share is in basis points and can't be more than 10,000"
+            );
+        }
+        if let Some(multiplier) = self.max_overpay_multiplier {
+            require!(multiplier >= 1, "max overpay multiplier must be at least 1");
+        }
         if let Some(r) = self.royalties.as_ref() {
             r.validate()
         }
@@ -78,6 +146,44 @@ impl Sale {
         }
     }
 }
+/// Tokens minted by `nft_mint_many_detailed` and what they cost
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintResult {
+    pub tokens: Vec<Token>,
+    /// Price paid, in cheddar when paying with cheddar
+    pub charged: U128,
+    /// NEAR sent back to the minter
+    pub refunded: U128,
+    /// NEAR paid for the storage of the tokens, prepaid storage excluded
+    pub storage_cost: U128,
+}
+
+/// Bit flags of what an admin can change, see `Contract::add_admin`
+pub struct Permission;
+
+impl Permission {
+    /// Prices, sale times and limits, royalties and cheddar payments
+    pub const MANAGE_SALE: u8 = 1;
+    /// Whitelist, allowlist and free mints
+    pub const MANAGE_WHITELIST: u8 = 1 << 1;
+    /// Token metadata, events, transfer lock and freeze
+    pub const MANAGE_TOKENS: u8 = 1 << 2;
+    pub const ALL: u8 = Self::MANAGE_SALE | Self::MANAGE_WHITELIST | Self::MANAGE_TOKENS;
+}
+
+/// Currency a mint is paid with
+#[witgen]
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PaymentMethod {
+    Near,
+    Cheddar,
+    /// Fungible token contract, CHEDDAR is the only one accepted
+    Token(AccountId),
+}
+
 /// Current state of contract
 #[witgen]
 #[derive(Serialize)]
@@ -91,6 +197,35 @@ pub enum Status {
     Open,
     /// No more tokens to be minted
     SoldOut,
+    /// Sale is over, no more tokens can be minted
+    Ended,
+}
+
+/// Mint made with a client supplied `request_id`, see `Contract::nft_mint_many`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MintRequest {
+    pub request_id: String,
+    pub at: TimestampMs,
+    pub token_ids: Vec<TokenId>,
+}
+
+/// Progress of counting the holders of a migrated contract, see `Contract::admin_count_holders`
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum HoldersCount {
+    /// Going over the tokens, after the given one if any
+    Counting(Option<TokenId>),
+    /// Every token was gone over, forgetting the accounts counted
+    Clearing,
+}
+
+/// Amounts paid for minted tokens since the start of the sale
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Revenue {
+    /// In yoctoNEAR, without storage costs
+    pub total_near_raised: U128,
+    pub total_cheddar_raised: U128,
 }
 
 /// Information about the current sale from user perspective
@@ -104,6 +239,18 @@ pub struct UserSaleInfo {
     pub remaining_allowance: Option<u32>,
 }
 
+/// What an account is allowed to do with the contract
+#[allow(dead_code)]
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Roles {
+    pub is_owner: bool,
+    pub is_admin: bool,
+    pub is_whitelisted: bool,
+    pub remaining_allowance: Option<u32>,
+}
+
 /// Information about the current sale
 #[allow(dead_code)]
 #[witgen]
@@ -116,6 +263,8 @@ pub struct SaleInfo {
     pub presale_start: TimestampMs,
     /// Start of public sale
     pub sale_start: TimestampMs,
+    /// End of public sale
+    pub sale_end: TimestampMs,
     /// Total tokens that could be minted
     pub token_final_supply: u64,
     /// Current price for one token
@@ -124,6 +273,39 @@ pub struct SaleInfo {
     pub tokens_sold: u32,
 }
 
+/// Progress of the raffle
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RaffleStats {
+    /// Size of the collection when created, burns don't lower it
+    pub initial_size: u32,
+    /// Number of tokens drawn, ids reopened by a burn count each time they are drawn
+    pub drawn: u32,
+    /// Tokens held for linkdrops not claimed yet
+    pub pending: u32,
+}
+
+/// Aggregate state of the minter, to fetch it in one call
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractStats {
+    /// Number of tokens in circulation
+    pub total_supply: U128,
+    /// Tokens left to be minted
+    pub tokens_left: u32,
+    /// Number of tokens minted
+    pub minted: u32,
+    /// Current state of contract
+    pub status: Status,
+    /// Current price for the next token
+    pub price_next: U128,
+    pub num_admins: u32,
+    /// Entries migrated from the first version only count once they changed
+    pub num_whitelisted: u32,
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
@@ -161,4 +343,43 @@ mod tests {
         let contract = new_contract();
         assert_eq!(contract.minting_cost(&account(), 1).0, TEN);
     }
+
+    #[test]
+    #[should_panic(expected = "presale price can't be higher than the public sale price")]
+    fn presale_price_above_price() {
+        let mut sale = Sale::new(TEN);
+        sale.presale_price = Some(U128(TEN + 1));
+        sale.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "presale must start before the public sale")]
+    fn presale_after_public_sale() {
+        let mut sale = Sale::new(TEN);
+        sale.presale_start = Some(2);
+        sale.public_sale_start = Some(1);
+        sale.validate();
+    }
+
+    #[test]
+    fn valid_sale() {
+        let mut sale = Sale::new(TEN);
+        sale.presale_price = Some(U128(TEN));
+        sale.presale_start = Some(1);
+        sale.public_sale_start = Some(1);
+        sale.validate();
+    }
+}
+
+/// Parameters of a fungible token accepted to pay for mints
+#[allow(dead_code)]
+#[witgen]
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenParametersOutput {
+    pub token_id: AccountId,
+    /// Amount of the token worth one NEAR, expressed in 1e3
+    pub rate: U128,
+    /// Discount in % when paying with the token
+    pub discount: u32,
 }
@@ -4,7 +4,7 @@
 
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
 use near_sdk::json_types::U128;
-use near_sdk::{env, ext_contract, log, AccountId, Balance, PromiseOrValue};
+use near_sdk::{env, ext_contract, log, AccountId, Balance, Promise, PromiseOrValue};
 
 use crate::*;
 
@@ -12,6 +12,84 @@ const E21: Balance = 1000_000000_000000_000000; // 1e21
 pub const MIN_BAL: Balance = E21 * 500; // 0.5
 pub const E24: u128 = E21 * 1000;
 
+/// CHEDDAR deposited by each account. The depositors are listed too, to refund them all when the
+/// sale winds down, see `Contract::admin_refund_all_token_deposits`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Deposits {
+    pub(crate) balances: LookupMap<AccountId, Balance>,
+    /// Accounts with a balance. Those which deposited before the list was added aren't in it.
+    accounts: UnorderedSet<AccountId>,
+}
+
+impl Deposits {
+    pub fn new(balances: LookupMap<AccountId, Balance>) -> Self {
+        Self {
+            balances,
+            accounts: UnorderedSet::new(StorageKey::CheddarDepositors),
+        }
+    }
+
+    pub fn get(&self, account_id: &AccountId) -> Option<Balance> {
+        self.balances.get(account_id)
+    }
+
+    pub fn insert(&mut self, account_id: &AccountId, amount: &Balance) -> Option<Balance> {
+        self.accounts.insert(account_id);
+        self.balances.insert(account_id, amount)
+    }
+
+    pub fn remove(&mut self, account_id: &AccountId) -> Option<Balance> {
+        self.accounts.remove(account_id);
+        self.balances.remove(account_id)
+    }
+
+    /// Up to `limit` listed depositors
+    pub fn depositors(&self, limit: u64) -> Vec<AccountId> {
+        let accounts = self.accounts.as_vector();
+        (0..u64::min(limit, accounts.len()))
+            .filter_map(|index| accounts.get(index))
+            .collect()
+    }
+
+    pub fn num_depositors(&self) -> u64 {
+        self.accounts.len()
+    }
+}
+
+/// Whitelisted accounts and their remaining allowance. Contracts migrated from the first version
+/// also read the former `LookupMap`, which can't be listed; an entry moves to `entries` when
+/// it changes.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Whitelist {
+    pub(crate) entries: UnorderedMap<AccountId, u32>,
+    legacy: LookupMap<AccountId, u32>,
+}
+
+impl Whitelist {
+    pub fn new(legacy: LookupMap<AccountId, u32>) -> Self {
+        Self {
+            entries: UnorderedMap::new(StorageKey::WhitelistEntries),
+            legacy,
+        }
+    }
+
+    pub fn get(&self, account_id: &AccountId) -> Option<u32> {
+        self.entries
+            .get(account_id)
+            .or_else(|| self.legacy.get(account_id))
+    }
+
+    pub fn insert(&mut self, account_id: &AccountId, allowance: &u32) -> Option<u32> {
+        let legacy = self.legacy.remove(account_id);
+        self.entries.insert(account_id, allowance).or(legacy)
+    }
+
+    pub fn remove(&mut self, account_id: &AccountId) -> Option<u32> {
+        let legacy = self.legacy.remove(account_id);
+        self.entries.remove(account_id).or(legacy)
+    }
+}
+
 // token deposits are done through NEP-141 ft_transfer_call to the NEARswap contract.
 #[near_bindgen]
 impl FungibleTokenReceiver for Contract {
@@ -20,10 +98,11 @@ impl FungibleTokenReceiver for Contract {
     Handles both farm deposits and stake deposits. For farm deposit (sending tokens
     to setup the farm) you must set "setup reward deposit" msg.
     Otherwise tokens will be staken.
-    Returns zero.
+    With `msg` `{"action":"mint","num":N}` the tokens pay for minting `N` NFTs right away and the
+    unused amount is refunded.
+    Returns zero, or the full amount to refund tokens other than CHEDDAR.
     Panics when:
     - account is not registered
-    - or receiving a wrong token
     - or making a farm deposit after farm is finalized
     - or staking before farm is finalized. */
     #[allow(unused_variables)]
@@ -34,7 +113,19 @@ impl FungibleTokenReceiver for Contract {
         msg: String,
     ) -> PromiseOrValue<U128> {
         let token = env::predecessor_account_id();
-        assert!(token == self.cheddar, "only CHEDDAR deposits are allowed()");
+        if token != self.cheddar {
+            log!(
+                "Only CHEDDAR deposits are allowed, refunding {} of {}",
+                amount.0,
+                token
+            );
+            return PromiseOrValue::Value(amount);
+        }
+        if !msg.is_empty() {
+            let TransferMsg::Mint { num } = near_sdk::serde_json::from_str(&msg)
+                .expect(r#"msg must be empty or {"action":"mint","num":N}"#);
+            return PromiseOrValue::Value(self.mint_with_transfer(sender_id, amount.0, num).into());
+        }
         if let Some(deposit) = self.cheddar_deposits.get(&sender_id) {
             self.cheddar_deposits
                 .insert(&sender_id, &(deposit + amount.0));
@@ -51,39 +142,92 @@ impl FungibleTokenReceiver for Contract {
     }
 }
 
+/// `msg` of a CHEDDAR `ft_transfer_call`, e.g. `{"action":"mint","num":2}`
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", tag = "action", rename_all = "snake_case")]
+enum TransferMsg {
+    Mint { num: u32 },
+}
+
+impl Contract {
+    /// Transfer `amount` of CHEDDAR, already taken out of `user`'s deposit, crediting it back
+    /// if the transfer fails
+    pub(crate) fn send_cheddar(&self, user: AccountId, amount: Balance) -> Promise {
+        ext_ft::ft_transfer(
+            user.clone(),
+            amount.into(),
+            Some("Cheddar TENK withdraw".to_string()),
+            self.cheddar.clone(),
+            1,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_self::on_withdraw_cheddar(
+            user,
+            amount.into(),
+            env::current_account_id(),
+            0,
+            GAS_FOR_WITHDRAW_CALLBACK,
+        ))
+    }
+
+    /// Mint `num` tokens to `sender_id` paying with the just transferred `amount` of CHEDDAR,
+    /// taking from the account's deposit if it isn't enough. Returns the unused part of `amount`.
+    /// The storage of the tokens needs to be prepaid, see `storage_deposit_of`.
+    fn mint_with_transfer(&mut self, sender_id: AccountId, amount: Balance, num: u32) -> Balance {
+        let deposit = self.cheddar_deposits.get(&sender_id).unwrap_or_default() + amount;
+        self.cheddar_deposits.insert(&sender_id, &deposit);
+        let payer = Payer {
+            account_id: &sender_id,
+            refund_to: &sender_id,
+            referrer: None,
+            request_id: None,
+        };
+        self.mint_checked(num, &payer, &sender_id, true);
+        let left = self.cheddar_deposits.get(&sender_id).unwrap_or_default();
+        let unused = amount.saturating_sub(deposit.saturating_sub(left));
+        if left == unused {
+            self.cheddar_deposits.remove(&sender_id);
+        } else {
+            self.cheddar_deposits.insert(&sender_id, &(left - unused));
+        }
+        unused
+    }
+}
+
 #[near_bindgen]
 impl Contract {
-    /// if amount == None, then we withdraw all Cheddar and unregister the user
-    pub fn withdraw_cheddar(&mut self, amount: Option<U128>) {
+    /// if amount == None, then we withdraw all Cheddar and unregister the user.
+    /// The amount is credited back if the transfer fails.
+    pub fn withdraw_cheddar(&mut self, amount: Option<U128>) -> Promise {
         let user = env::predecessor_account_id();
-        let mut deposit = self
+        let deposit = self
             .cheddar_deposits
             .get(&user)
             .expect("account deposit is empty");
-
-        if amount.is_none() {
+        let amount = amount.map_or(deposit, |a| a.0);
+        assert!(deposit >= amount, "not enough deposit");
+        if deposit == amount {
             log!("Unregistering account {}", user);
             self.cheddar_deposits.remove(&user);
         } else {
-            let amount = amount.unwrap().0;
-            assert!(deposit >= amount, "not enough deposit");
-            if deposit == amount {
-                log!("Unregistering account {}", user);
-                self.cheddar_deposits.remove(&user);
-            } else {
-                deposit -= amount;
-                assert!(deposit > MIN_BAL, "When withdrawing, either withdraw everyting to unregister or keep at least 1Cheddar");
-                self.cheddar_deposits.insert(&user, &(deposit));
-            }
+            let deposit = deposit - amount;
+            assert!(deposit > MIN_BAL, "When withdrawing, either withdraw everyting to unregister or keep at least 1Cheddar");
+            self.cheddar_deposits.insert(&user, &deposit);
+        }
+        self.send_cheddar(user, amount)
+    }
+
+    #[private]
+    pub fn on_withdraw_cheddar(&mut self, account_id: AccountId, amount: U128) {
+        if !is_promise_success(None) {
+            log!(
+                "Cheddar withdraw failed, crediting back {} to {}",
+                amount.0,
+                account_id
+            );
+            let deposit = self.cheddar_deposits.get(&account_id).unwrap_or_default() + amount.0;
+            self.cheddar_deposits.insert(&account_id, &deposit);
         }
-        ext_ft::ft_transfer(
-            user,
-            deposit.into(),
-            Some("Cheddar TENK withdraw".to_string()),
-            self.cheddar.clone(),
-            1,
-            GAS_FOR_FT_TRANSFER,
-        );
     }
 
     /// returns user Cheddar balance
@@ -93,6 +237,16 @@ impl Contract {
             .unwrap_or_default()
             .into()
     }
+
+    /// returns user Cheddar balance deposited in the contract, same as `balance_of`
+    pub fn cheddar_balance_of(&self, account_id: &AccountId) -> U128 {
+        self.balance_of(account_id)
+    }
+
+    /// Cheddar needed by `minter` to mint `num` tokens, to compare with `cheddar_balance_of`
+    pub fn cheddar_mint_cost(&self, num: u32, minter: &AccountId) -> U128 {
+        self.total_cost(num, minter, true)
+    }
 }
 
 #[ext_contract(ext_ft)]
@@ -100,3 +254,105 @@ pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
     fn ft_mint(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+    fn deposit(contract: &mut Contract, account_id: AccountId, amount: Balance) {
+        set_context(&cheddar(), 0);
+        contract.ft_on_transfer(account_id, U128(amount), "".to_string());
+    }
+
+    #[test]
+    fn withdraw_cheddar() {
+        let mut contract = new_contract();
+        deposit(&mut contract, alice(), 100 * ONE_NEAR);
+        set_context(&alice(), 0);
+        contract.withdraw_cheddar(Some(U128(40 * ONE_NEAR)));
+        assert_eq!(contract.balance_of(&alice()).0, 60 * ONE_NEAR);
+        contract.withdraw_cheddar(None);
+        assert_eq!(contract.balance_of(&alice()).0, 0);
+        assert!(contract.cheddar_deposits.get(&alice()).is_none());
+    }
+
+    #[test]
+    fn failed_withdraw_credited_back() {
+        let mut contract = new_contract();
+        deposit(&mut contract, alice(), 100 * ONE_NEAR);
+        set_context(&alice(), 0);
+        contract.withdraw_cheddar(None);
+
+        testing_env!(
+            context(&tenk()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.on_withdraw_cheddar(alice(), U128(100 * ONE_NEAR));
+        assert_eq!(contract.balance_of(&alice()).0, 100 * ONE_NEAR);
+    }
+
+    #[test]
+    fn cheddar_balance() {
+        let mut contract = new_contract();
+        assert_eq!(contract.cheddar_balance_of(&alice()).0, 0);
+
+        deposit(&mut contract, alice(), 100 * ONE_NEAR);
+        assert_eq!(contract.cheddar_balance_of(&alice()).0, 100 * ONE_NEAR);
+        assert_eq!(contract.balance_of(&alice()).0, 100 * ONE_NEAR);
+
+        // 1 NEAR = 10 cheddar with a 10% discount
+        assert_eq!(contract.cheddar_mint_cost(1, &alice()).0, 90 * ONE_NEAR);
+        assert_eq!(contract.cheddar_mint_cost(2, &alice()).0, 180 * ONE_NEAR);
+        assert_eq!(contract.cheddar_mint_cost(1, &owner()).0, 0);
+    }
+
+    #[test]
+    fn unknown_token_refunded() {
+        let mut contract = new_contract();
+        set_context(&AccountId::new_unchecked("stray.near".to_string()), 0);
+        let refund = contract.ft_on_transfer(alice(), U128(100 * ONE_NEAR), "".to_string());
+        assert!(matches!(refund, PromiseOrValue::Value(U128(amount)) if amount == 100 * ONE_NEAR));
+        assert_eq!(contract.balance_of(&alice()).0, 0);
+    }
+
+    /// Contract paying the price to the owner, with the storage of alice's mints prepaid
+    fn transfer_mint_contract() -> Contract {
+        let mut contract = new_contract();
+        contract.sale.initial_royalties = Some(Royalties::default());
+        contract
+            .storage_deposits
+            .insert(&alice(), &(10 * contract.token_storage_cost().0));
+        contract
+    }
+
+    #[test]
+    fn mint_with_transfer() {
+        let mut contract = transfer_mint_contract();
+        set_context(&cheddar(), 0);
+        let cost = contract.cheddar_mint_cost(2, &alice()).0;
+        let msg = r#"{"action":"mint","num":2}"#.to_string();
+        let refund = contract.ft_on_transfer(alice(), U128(cost), msg);
+        assert!(matches!(refund, PromiseOrValue::Value(U128(0))));
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 2);
+        assert_eq!(contract.balance_of(&alice()).0, 0);
+        assert_eq!(contract.balance_of(&owner()).0, cost);
+    }
+
+    #[test]
+    fn mint_with_transfer_refunds_unused() {
+        let mut contract = transfer_mint_contract();
+        deposit(&mut contract, alice(), 100 * ONE_NEAR);
+        let cost = contract.cheddar_mint_cost(1, &alice()).0;
+        let msg = r#"{"action":"mint","num":1}"#.to_string();
+        let refund = contract.ft_on_transfer(alice(), U128(cost + ONE_NEAR), msg);
+        assert!(matches!(refund, PromiseOrValue::Value(U128(amount)) if amount == ONE_NEAR));
+        assert_eq!(contract.nft_supply_for_owner(alice()).0, 1);
+        assert_eq!(contract.balance_of(&alice()).0, 100 * ONE_NEAR);
+    }
+}
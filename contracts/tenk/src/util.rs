@@ -1,8 +1,13 @@
 use near_contract_standards::non_fungible_token::{events::NftMint, Token};
-use near_sdk::{env, AccountId, Promise, PromiseResult};
+use near_sdk::{env, serde::Serialize, serde_json, AccountId, Promise, PromiseResult};
 
 use crate::TimestampMs;
 
+/// Standard of the `nft_mint` events, see NEP-171
+pub const NFT_EVENT_STANDARD: &str = "nep171";
+/// Version of the standard of the `nft_mint` events
+pub const NFT_EVENT_VERSION: &str = "1.0.0";
+
 pub fn is_promise_success(num_of_promises: Option<u64>) -> bool {
     let count = env::promise_results_count();
     if num_of_promises.map_or(false, |num| num != count) {
@@ -26,6 +31,15 @@ pub fn get_random_number(shift_amount: u32) -> u32 {
     u32::from_le_bytes(arr)
 }
 
+/// Deterministic number derived from `seed` and `nonce`
+#[cfg(feature = "testnet")]
+pub fn get_seeded_random_number(seed: &[u8], nonce: u64) -> u32 {
+    let hash = env::sha256(&[seed, &nonce.to_le_bytes()].concat());
+    let mut arr: [u8; 4] = Default::default();
+    arr.copy_from_slice(&hash[..4]);
+    u32::from_le_bytes(arr)
+}
+
 pub fn refund(account_id: &AccountId, amount: u128) -> Option<Promise> {
     if amount > 0 {
         return Some(Promise::new(account_id.clone()).transfer(amount));
@@ -42,10 +56,67 @@ pub fn log_mint(owner_id: &AccountId, tokens: &[Token]) {
         .iter()
         .map(|t| t.token_id.as_str())
         .collect::<Vec<&str>>();
-    NftMint {
+    emit_mints(&[NftMint {
         owner_id,
         token_ids,
         memo: None,
+    }])
+}
+
+/// Log one mint event for tokens minted to different owners
+pub fn log_mints(tokens: &[Token]) {
+    let token_ids: Vec<[&str; 1]> = tokens.iter().map(|t| [t.token_id.as_str()]).collect();
+    let mints: Vec<NftMint> = tokens
+        .iter()
+        .zip(&token_ids)
+        .map(|(token, token_ids)| NftMint {
+            owner_id: &token.owner_id,
+            token_ids,
+            memo: None,
+        })
+        .collect();
+    emit_mints(&mints)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct MintLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    event: &'static str,
+    data: &'a [NftMint<'a>],
+}
+
+/// Log an `nft_mint` event of `NFT_EVENT_STANDARD` and `NFT_EVENT_VERSION`
+fn emit_mints(mints: &[NftMint]) {
+    let log = MintLog {
+        standard: NFT_EVENT_STANDARD,
+        version: NFT_EVENT_VERSION,
+        event: "nft_mint",
+        data: mints,
+    };
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        serde_json::to_string(&log).unwrap_or_else(|_| env::abort())
+    ));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    #[test]
+    fn mint_log() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(2, &alice(), true, false);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            &format!(
+                r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{{"owner_id":"alice.near","token_ids":["{}","{}"]}}]}}"#,
+                tokens[0].token_id, tokens[1].token_id
+            )
+        );
     }
-    .emit()
 }
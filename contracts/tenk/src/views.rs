@@ -12,47 +12,179 @@ impl Contract {
         self.admins.to_vec()
     }
 
+    /// `Permission` flags of an admin, all of them for the owner and none for other accounts
+    pub fn admin_permissions(&self, account_id: AccountId) -> u8 {
+        if self.is_owner(&account_id) {
+            Permission::ALL
+        } else if self.is_admin(&account_id) {
+            self.admin_permissions
+                .get(&account_id)
+                .unwrap_or(Permission::ALL)
+        } else {
+            0
+        }
+    }
+
     /// Check whether an account is allowed to mint during the presale
     pub fn whitelisted(&self, account_id: &AccountId) -> bool {
-        self.whitelist.contains_key(account_id)
+        self.whitelist.get(account_id).is_some()
     }
 
-    /*
-        /// Cost of NFT + fees for linkdrop
-        pub fn cost_of_linkdrop(&self, minter: &AccountId) -> U128 {
-            (self.full_link_price(minter)
-                + self.total_cost(1, minter, false).0
-                + self.token_storage_cost().0)
-                .into()
+    /// Roles of `account_id`, to check what a wallet can do in one call
+    pub fn get_roles(&self, account_id: AccountId) -> Roles {
+        Roles {
+            is_owner: self.is_owner(&account_id),
+            is_admin: self.is_admin(&account_id),
+            is_whitelisted: self.whitelisted(&account_id),
+            remaining_allowance: self.remaining_allowance(&account_id),
         }
-    */
+    }
+
+    /// Cost of NFT + fees for linkdrop
+    pub fn cost_of_linkdrop(&self, minter: &AccountId) -> U128 {
+        (self.full_link_price(minter)
+            + self.total_cost(1, minter, false).0
+            + self.token_storage_cost().0)
+            .into()
+    }
 
     pub fn total_cost(&self, num: u32, minter: &AccountId, with_cheddar: bool) -> U128 {
         let mut cost = self.minting_cost(minter, num).0;
         if with_cheddar {
-            cost = cost / 1000 * self.cheddar_near / 100 * self.cheddar_boost as u128;
+            cost = self.cheddar_cost(cost);
         }
         cost.into()
     }
 
-    /// Flat cost in NEAR for minting given amount of tokens
+    /// Price of `num` tokens for `minter` in the smallest unit of the `payment` currency,
+    /// storage excluded. Panics if the currency isn't accepted.
+    pub fn effective_price(&self, num: u32, minter: AccountId, payment: PaymentMethod) -> U128 {
+        let with_cheddar = match payment {
+            PaymentMethod::Near => false,
+            PaymentMethod::Cheddar => true,
+            PaymentMethod::Token(token_id) => {
+                require!(
+                    token_id == self.cheddar,
+                    format!("{} is not accepted as payment", token_id)
+                );
+                true
+            }
+        };
+        require!(
+            !with_cheddar || self.accepts_cheddar,
+            "Paying with cheddar is not accepted"
+        );
+        self.total_cost(num, &minter, with_cheddar)
+    }
+
+    /// Whether tokens can be paid with cheddar, i.e. `with_cheddar` is allowed when minting
+    pub fn accepts_cheddar(&self) -> bool {
+        self.accepts_cheddar
+    }
+
+    /// Marketplace approved for each token at mint, see `admin_set_auto_approve_account`
+    pub fn default_approved_marketplace(&self) -> Option<AccountId> {
+        self.auto_approve_account.clone()
+    }
+
+    /// Whether the contract is frozen, see `admin_freeze_all`
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Payment parameters of the fungible token `token_id`, None if it can't be used to pay.
+    /// CHEDDAR is the only such token.
+    pub fn token_parameters(&self, token_id: &AccountId) -> Option<TokenParametersOutput> {
+        (*token_id == self.cheddar && self.accepts_cheddar).then(|| TokenParametersOutput {
+            token_id: token_id.clone(),
+            rate: self.cheddar_near.into(),
+            discount: 100 - self.cheddar_boost,
+        })
+    }
+
+    /// Amount of cheddar to pay instead of `near_amount`, using the current rate and boost
+    pub fn cheddar_example(&self, near_amount: U128) -> U128 {
+        self.cheddar_cost(near_amount.0).into()
+    }
+
+    /// Flat cost in NEAR for minting given amount of tokens.
+    /// The first tokens are free as long as the minter has free mints left.
     pub fn minting_cost(&self, minter: &AccountId, num: u32) -> U128 {
         if self.is_owner(minter) {
             0
         } else {
-            self.price(num)
+            let free = u32::min(self.free_mints_of(minter), num);
+            self.apply_holder_discount(minter, self.price(num) - self.price(free))
         }
         .into()
     }
 
+    /// Price of each of the next `num` tokens, without discounts. Tokens get more expensive with
+    /// each generation, so one batch can span several prices. Limited to the tokens left.
+    pub fn price_schedule(&self, num: u32) -> Vec<U128> {
+        let start_price = self.start_price();
+        (self.counter..)
+            .take(u32::min(num, self.tokens_left()) as usize)
+            .map(|counter| compute_unit_price(counter, start_price).into())
+            .collect()
+    }
+
+    /// Number of tokens the account can still mint without paying the price, only storage
+    pub fn free_mints_of(&self, account_id: &AccountId) -> u32 {
+        self.free_mint_list.get(account_id).unwrap_or_default()
+    }
+
     /// Current cost in NEAR to store one NFT
     pub fn token_storage_cost(&self) -> U128 {
         (env::storage_byte_cost() * self.tokens.extra_storage_in_bytes_per_token as Balance).into()
     }
 
+    /// Cost in NEAR to store one NFT at the current `storage_byte_cost`, same as
+    /// `token_storage_cost`, to quote the storage part of a mint. Mints are charged the storage
+    /// they actually use at the byte cost of the time, so nothing is refunded if it drops later.
+    pub fn effective_storage_cost_per_token(&self) -> U128 {
+        self.token_storage_cost()
+    }
+
+    /// NEAR prepaid for the storage of the account's future mints
+    pub fn storage_deposit_of(&self, account_id: &AccountId) -> U128 {
+        self.storage_deposits
+            .get(account_id)
+            .unwrap_or_default()
+            .into()
+    }
+
     /// Tokens left to be minted.  This includes those left to be raffled minus any pending linkdrops
     pub fn tokens_left(&self) -> u32 {
-        self.raffle.len() as u32 - self.pending_tokens
+        (self.raffle.len() as u32)
+            .checked_sub(self.pending_tokens)
+            .unwrap_or_else(|| {
+                log!(
+                    "Inconsistent state: {} pending tokens but only {} left in the raffle",
+                    self.pending_tokens,
+                    self.raffle.len()
+                );
+                0
+            })
+    }
+
+    /// When the last token was minted. Stays set if burned tokens reopen the collection.
+    pub fn sold_out_at(&self) -> Option<TimestampMs> {
+        self.sold_out_at
+    }
+
+    /// Initial size of the collection, number of tokens drawn from the raffle and pending linkdrops
+    pub fn raffle_stats(&self) -> RaffleStats {
+        RaffleStats {
+            initial_size: self.initial_size,
+            drawn: self.counter,
+            pending: self.pending_tokens,
+        }
+    }
+
+    /// Number of tokens held by the contract account itself, e.g. an undistributed reserve
+    pub fn contract_held_tokens(&self) -> u32 {
+        self.nft_supply_for_owner(env::current_account_id()).0 as u32
     }
 
     /// Part of the NFT metadata standard. Returns the contract's metadata
@@ -60,6 +192,16 @@ impl Contract {
         self.metadata.get().unwrap()
     }
 
+    /// Whitelisted accounts with their remaining allowance, `limit` entries starting at `from_index`.
+    /// Entries migrated from the first version are only listed once they changed.
+    pub fn whitelist_entries(&self, from_index: u64, limit: u64) -> Vec<(AccountId, u32)> {
+        let keys = self.whitelist.entries.keys_as_vector();
+        let values = self.whitelist.entries.values_as_vector();
+        (from_index..u64::min(from_index.saturating_add(limit), keys.len()))
+            .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap()))
+            .collect()
+    }
+
     /// How many tokens an account is still allowed to mint. None, means unlimited
     pub fn remaining_allowance(&self, account_id: &AccountId) -> Option<u32> {
         self.whitelist.get(account_id)
@@ -70,11 +212,43 @@ impl Contract {
         self.sale.mint_rate_limit
     }
 
+    /// Whether a non-owner account could mint right now, i.e. presale or public sale
+    /// is running, there are tokens left and the contract isn't frozen
+    pub fn mint_live(&self) -> bool {
+        if self.frozen {
+            return false;
+        }
+        match self.get_status() {
+            Status::Presale => self.presale_tokens_left() > 0,
+            Status::Open => self.tokens_left() > 0,
+            Status::Closed | Status::SoldOut | Status::Ended => false,
+        }
+    }
+
+    /// Max number of tokens a buyer can mint in one transaction right now,
+    /// i.e. the smallest of `MAX_MINT_BATCH`, `mint_rate_limit` and tokens left (capped by the presale supply during presale).
+    /// Zero when the sale is closed, sold out or the contract is frozen.
+    pub fn effective_batch_limit(&self) -> u32 {
+        if self.frozen {
+            return 0;
+        }
+        let left = match self.get_status() {
+            Status::Closed | Status::SoldOut | Status::Ended => return 0,
+            Status::Presale => self.presale_tokens_left(),
+            Status::Open => self.tokens_left(),
+        };
+        self.sale
+            .mint_rate_limit
+            .map_or(left, |limit| u32::min(limit, left))
+            .min(MAX_MINT_BATCH)
+    }
+
     /// Information about the current sale. When in starts, status, price, and how many could be minted.
     pub fn get_sale_info(&self) -> SaleInfo {
         SaleInfo {
             presale_start: self.sale.presale_start.unwrap_or(MAX_DATE),
             sale_start: self.sale.public_sale_start.unwrap_or(MAX_DATE),
+            sale_end: self.sale.public_sale_end.unwrap_or(MAX_DATE),
             status: self.get_status(),
             price: self.price(1).into(),
             token_final_supply: self.initial(),
@@ -82,6 +256,19 @@ impl Contract {
         }
     }
 
+    /// Supply, sale and access control stats of the contract in one call
+    pub fn get_contract_stats(&self) -> ContractStats {
+        ContractStats {
+            total_supply: self.nft_total_supply(),
+            tokens_left: self.tokens_left(),
+            minted: self.counter,
+            status: self.get_status(),
+            price_next: self.price(1).into(),
+            num_admins: self.admins.len() as u32,
+            num_whitelisted: self.whitelist.entries.len() as u32,
+        }
+    }
+
     /// Information about a current user. Whether they are VIP and how many tokens left in their allowance.
     pub fn get_user_sale_info(&self, account_id: &AccountId) -> UserSaleInfo {
         let sale_info = self.get_sale_info();
@@ -97,8 +284,531 @@ impl Contract {
         }
     }
 
+    /// Tokens of `account_id` which haven't expired, i.e. without `expires_at` or with
+    /// `expires_at` (in milliseconds) not in the past. Pagination is applied before filtering.
+    pub fn nft_tokens_for_owner_valid(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let now = current_time_ms();
+        self.nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .filter(|token| {
+                let expires_at = token
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.expires_at.as_ref())
+                    .and_then(|expires_at| expires_at.parse::<TimestampMs>().ok());
+                !matches!(expires_at, Some(expires_at) if expires_at < now)
+            })
+            .collect()
+    }
+
+    /// Token ids of `account_id` as numbers, paginated like `nft_tokens_for_owner`
+    pub fn minted_ids_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<u64> {
+        self.nft_tokens_for_owner(account_id, from_index, limit)
+            .into_iter()
+            .filter_map(|token| match token.token_id.parse() {
+                Ok(id) => Some(id),
+                Err(_) => {
+                    log!("Skipping token {}, its id is not a number", token.token_id);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Number of tokens ever minted. Unlike `nft_total_supply` burns don't reduce it,
+    /// it's the count driving the price.
+    pub fn nft_total_minted(&self) -> u32 {
+        self.counter
+    }
+
+    /// When `token_id` was minted, None for tokens minted before this was recorded
+    pub fn token_minted_at(&self, token_id: TokenId) -> Option<TimestampMs> {
+        self.token_minted_at.get(&token_id)
+    }
+
+    /// Number of accounts owning at least one token, unknown until `admin_count_holders` is
+    /// done for contracts migrated from the first version
+    pub fn num_holders(&self) -> Option<u64> {
+        match self.holders_count {
+            Some(HoldersCount::Counting(_)) => None,
+            _ => Some(self.num_holders),
+        }
+    }
+
+    /// Amounts paid for minted tokens, in NEAR and in cheddar
+    pub fn get_revenue(&self) -> Revenue {
+        Revenue {
+            total_near_raised: self.total_near_raised.into(),
+            total_cheddar_raised: self.total_cheddar_raised.into(),
+        }
+    }
+
+    /// Tokens which can still be minted during the presale, limited by `Sale::presale_supply_cap`
+    pub fn presale_tokens_left(&self) -> u32 {
+        let left = self.tokens_left();
+        self.sale.presale_supply_cap.map_or(left, |cap| {
+            u32::min(left, cap.saturating_sub(self.presale_minted))
+        })
+    }
+
+    /// Value in yoctoNEAR of the referral bonuses the account received
+    pub fn referral_earnings(&self, account_id: &AccountId) -> U128 {
+        self.referral_earnings
+            .get(account_id)
+            .unwrap_or_default()
+            .into()
+    }
+
+    /// Whether the token can't be transferred
+    pub fn is_soulbound(&self, token_id: TokenId) -> bool {
+        self.soulbound.contains_key(&token_id)
+    }
+
+    /// Whether the token was already minted
+    pub fn nft_is_minted(&self, token_id: TokenId) -> bool {
+        self.tokens.owner_by_id.contains_key(&token_id)
+    }
+
+    /// Up to `limit` token ids which can still be drawn, in no particular order
+    pub fn remaining_sample(&self, limit: u32) -> Vec<u64> {
+        (0..u64::min(limit as u64, self.raffle.len()))
+            .filter_map(|index| self.raffle.get(index))
+            .collect()
+    }
+
+    /// Once sold out, all token ids in the order they were drawn, to verify the raffle.
+    /// Draws made before a migration from the first version weren't recorded.
+    pub fn final_provenance(&self) -> Vec<u32> {
+        self.final_provenance_page(0, self.draw_order.len() as u32)
+    }
+
+    /// Once sold out, `limit` token ids in the order they were drawn, starting at draw `from`
+    pub fn final_provenance_page(&self, from: u32, limit: u32) -> Vec<u32> {
+        require!(self.tokens_left() == 0, "Provenance is final once sold out");
+        (from as u64..u64::min(from as u64 + limit as u64, self.draw_order.len()))
+            .filter_map(|index| self.draw_order.get(index))
+            .collect()
+    }
+
     /// Initial size of collection. Number left to raffle + current total supply
     pub fn initial(&self) -> u64 {
         self.raffle.len() + self.nft_total_supply().0 as u64
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::get_logs;
+
+    #[test]
+    fn contract_held_tokens() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(3, &tenk(), true, false);
+        assert_eq!(contract.contract_held_tokens(), 3);
+
+        set_context(&tenk(), 1);
+        contract.nft_transfer(alice(), tokens[0].token_id.clone(), None, None);
+        assert_eq!(contract.contract_held_tokens(), 2);
+    }
+
+    #[test]
+    fn get_roles() {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), None);
+        contract.admin_add_whitelist(vec![alice()], 2);
+
+        let owner_roles = contract.get_roles(owner());
+        assert!(owner_roles.is_owner && !owner_roles.is_admin && !owner_roles.is_whitelisted);
+        let admin_roles = contract.get_roles(bob());
+        assert!(!admin_roles.is_owner && admin_roles.is_admin && !admin_roles.is_whitelisted);
+        let whitelisted_roles = contract.get_roles(alice());
+        assert!(!whitelisted_roles.is_owner && !whitelisted_roles.is_admin);
+        assert!(whitelisted_roles.is_whitelisted);
+        assert_eq!(whitelisted_roles.remaining_allowance, Some(2));
+        let roles = contract.get_roles(cheddar());
+        assert!(!roles.is_owner && !roles.is_admin && !roles.is_whitelisted);
+        assert_eq!(roles.remaining_allowance, None);
+    }
+
+    #[test]
+    fn effective_storage_cost_per_token() {
+        let contract = new_contract();
+        assert_eq!(
+            contract.effective_storage_cost_per_token(),
+            contract.token_storage_cost()
+        );
+    }
+
+    #[test]
+    fn token_minted_at() {
+        let mut contract = new_contract();
+        set_time(&owner(), NOW + 1_000);
+        let tokens = contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        assert_eq!(
+            contract.token_minted_at(tokens[0].token_id.clone()),
+            Some(NOW + 1_000)
+        );
+        assert_eq!(contract.token_minted_at("unminted".to_string()), None);
+    }
+
+    #[test]
+    fn effective_price() {
+        let contract = new_contract();
+        let near = contract.effective_price(2, alice(), PaymentMethod::Near);
+        assert_eq!(near, contract.total_cost(2, &alice(), false));
+        assert_eq!(near.0, 2 * PRICE);
+        let cheddar_price = contract.effective_price(2, alice(), PaymentMethod::Cheddar);
+        assert_eq!(cheddar_price, contract.total_cost(2, &alice(), true));
+        // 1 NEAR = 10 cheddar with a 10% discount
+        assert_eq!(cheddar_price.0, 18 * PRICE);
+        assert_eq!(
+            contract.effective_price(2, alice(), PaymentMethod::Token(cheddar())),
+            cheddar_price
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "bob.near is not accepted as payment")]
+    fn effective_price_other_token() {
+        let contract = new_contract();
+        contract.effective_price(1, alice(), PaymentMethod::Token(bob()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Paying with cheddar is not accepted")]
+    fn effective_price_cheddar_not_accepted() {
+        let mut contract = new_contract();
+        contract.accepts_cheddar = false;
+        contract.effective_price(1, alice(), PaymentMethod::Cheddar);
+    }
+
+    #[test]
+    fn token_parameters() {
+        let mut contract = new_contract();
+        let params = contract.token_parameters(&cheddar()).unwrap();
+        assert_eq!(params.token_id, cheddar());
+        assert_eq!(params.rate.0, contract.cheddar_near);
+        assert_eq!(params.discount, 10);
+        assert!(contract.token_parameters(&alice()).is_none());
+
+        contract.accepts_cheddar = false;
+        assert!(contract.token_parameters(&cheddar()).is_none());
+    }
+
+    #[test]
+    fn cheddar_example() {
+        let mut contract = new_contract();
+        // 1 NEAR = 10 cheddar, 10% discount
+        assert_eq!(contract.cheddar_example(U128(ONE_NEAR)).0, 9 * ONE_NEAR);
+        assert_eq!(contract.cheddar_example(U128(PRICE)).0, 90 * ONE_NEAR);
+        assert_eq!(contract.cheddar_example(U128(0)).0, 0);
+
+        contract.cheddar_boost = 50;
+        assert_eq!(contract.cheddar_example(U128(ONE_NEAR)).0, 5 * ONE_NEAR);
+        contract.cheddar_boost = 100;
+        assert_eq!(contract.cheddar_example(U128(ONE_NEAR / 2)).0, 5 * ONE_NEAR);
+        assert_eq!(
+            contract.cheddar_example(U128(PRICE)),
+            contract.total_cost(1, &alice(), true)
+        );
+    }
+
+    #[test]
+    fn holder_discount() {
+        let mut sale = open_sale();
+        sale.holder_discount_bps = Some(2_000);
+        let mut contract = new_contract_with_sale(sale);
+        assert_eq!(contract.minting_cost(&alice(), 1).0, PRICE);
+
+        set_context(&alice(), PRICE + ONE_NEAR);
+        contract.nft_mint_one(false);
+        assert_eq!(contract.minting_cost(&alice(), 1).0, PRICE * 8 / 10);
+        assert_eq!(contract.minting_cost(&bob(), 1).0, PRICE);
+        assert_eq!(contract.minting_cost(&owner(), 1).0, 0);
+
+        // the discount applies to the presale price as well
+        contract.sale.public_sale_start = None;
+        contract.sale.presale_start = Some(0);
+        contract.sale.presale_price = Some(U128(PRICE / 2));
+        assert_eq!(contract.minting_cost(&alice(), 1).0, PRICE * 4 / 10);
+    }
+
+    #[test]
+    fn contract_stats() {
+        let mut contract = new_contract();
+        contract.add_admin(bob(), None);
+        contract.admin_add_whitelist(vec![alice(), bob()], 2);
+        contract.admin_add_whitelist(vec![alice()], 3);
+        contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+
+        let stats = contract.get_contract_stats();
+        assert_eq!(stats.total_supply, contract.nft_total_supply());
+        assert_eq!(stats.tokens_left, contract.tokens_left());
+        assert_eq!(stats.minted, 3);
+        assert!(matches!(stats.status, Status::Open));
+        assert_eq!(stats.price_next, contract.get_sale_info().price);
+        assert_eq!(stats.num_admins, 1);
+        assert_eq!(stats.num_whitelisted, 2);
+
+        contract.admin_remove_whitelist(vec![alice(), alice()]);
+        assert_eq!(contract.get_contract_stats().num_whitelisted, 1);
+    }
+
+    #[test]
+    fn sold_out_at() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(SIZE - 1, &alice(), true, false);
+        assert_eq!(contract.sold_out_at(), None);
+
+        set_time(&alice(), NOW + 1);
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        assert_eq!(contract.sold_out_at(), Some(NOW + 1));
+        let sold_out: Vec<String> = get_logs()
+            .into_iter()
+            .filter(|log| log.contains(r#""event":"sold_out""#))
+            .collect();
+        assert_eq!(
+            sold_out,
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"tenk","version":"1.0.0","event":"sold_out","data":[{{"sold_out_at":{},"minted":{}}}]}}"#,
+                NOW + 1,
+                SIZE
+            )]
+        );
+
+        // selling out again after a burn reopened the collection doesn't change it
+        contract.reopen_on_burn = true;
+        set_context(&alice(), 1);
+        let token_id = contract.nft_tokens_for_owner(alice(), None, Some(1))[0]
+            .token_id
+            .clone();
+        contract.nft_burn(token_id);
+        set_time(&alice(), NOW + 2);
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        assert_eq!(contract.sold_out_at(), Some(NOW + 1));
+        assert!(!get_logs().iter().any(|log| log.contains("sold_out")));
+    }
+
+    #[test]
+    fn whitelist_entries() {
+        let mut contract = new_contract();
+        contract.admin_add_whitelist_tiered(vec![(alice(), 1), (bob(), 2), (owner(), 3)]);
+        assert_eq!(
+            contract.whitelist_entries(0, 2),
+            vec![(alice(), 1), (bob(), 2)]
+        );
+        assert_eq!(contract.whitelist_entries(2, 2), vec![(owner(), 3)]);
+        assert!(contract.whitelist_entries(3, 2).is_empty());
+
+        contract.admin_remove_whitelist(vec![alice()]);
+        let mut entries = contract.whitelist_entries(0, 10);
+        entries.sort();
+        assert_eq!(entries, vec![(bob(), 2), (owner(), 3)]);
+        assert!(!contract.whitelisted(&alice()));
+        assert_eq!(contract.remaining_allowance(&bob()), Some(2));
+    }
+
+    #[test]
+    fn raffle_stats() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        contract.pending_tokens = 2;
+        let stats = contract.raffle_stats();
+        assert_eq!(
+            (stats.initial_size, stats.drawn, stats.pending),
+            (SIZE, 3, 2)
+        );
+        assert_eq!(contract.tokens_left(), SIZE - 5);
+
+        // burning doesn't shrink the initial size
+        set_context(&alice(), 1);
+        contract.nft_burn(tokens[0].token_id.clone());
+        assert_eq!(contract.raffle_stats().initial_size, SIZE);
+    }
+
+    #[test]
+    fn tokens_left_saturates() {
+        let mut contract = new_contract();
+        contract.pending_tokens = SIZE + 1;
+        assert_eq!(contract.tokens_left(), 0);
+        assert!(get_logs()[0].starts_with("Inconsistent state"));
+    }
+
+    #[test]
+    fn minted_ids_for_owner() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(4, &alice(), true, false);
+        contract.nft_mint_many_ungaurded(1, &bob(), true, false);
+        let mut expected: Vec<u64> = tokens
+            .iter()
+            .map(|token| token.token_id.parse().unwrap())
+            .collect();
+        let mut ids = contract.minted_ids_for_owner(alice(), None, None);
+        expected.sort_unstable();
+        ids.sort_unstable();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            contract
+                .minted_ids_for_owner(alice(), Some(U128(1)), Some(2))
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn nft_tokens_for_owner_valid() {
+        let mut contract = new_contract();
+        let tokens = contract.nft_mint_many_ungaurded(3, &alice(), true, false);
+        contract.admin_set_token_expiry(tokens[0].token_id.clone(), Some(NOW - 1));
+        contract.admin_set_token_expiry(tokens[1].token_id.clone(), Some(NOW));
+
+        let valid: Vec<TokenId> = contract
+            .nft_tokens_for_owner_valid(alice(), None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        assert_eq!(valid.len(), 2);
+        assert!(!valid.contains(&tokens[0].token_id));
+
+        contract.admin_set_token_expiry(tokens[0].token_id.clone(), None);
+        assert_eq!(
+            contract
+                .nft_tokens_for_owner_valid(alice(), None, None)
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn nft_is_minted() {
+        let mut contract = new_contract();
+        let minted: Vec<TokenId> = contract
+            .nft_mint_many_ungaurded(5, &alice(), true, false)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect();
+        for id in 0..SIZE {
+            let token_id = id.to_string();
+            assert_eq!(
+                contract.nft_is_minted(token_id.clone()),
+                minted.contains(&token_id)
+            );
+        }
+
+        let len = contract.tokens_left();
+        let sample = contract.remaining_sample(SIZE);
+        assert_eq!(sample.len(), len as usize);
+        assert_eq!(contract.tokens_left(), len);
+        assert!(sample
+            .iter()
+            .all(|id| !contract.nft_is_minted(id.to_string())));
+        assert_eq!(contract.remaining_sample(3), sample[..3].to_vec());
+    }
+
+    #[test]
+    fn final_provenance() {
+        let mut contract = new_contract();
+        let mut minted: Vec<u32> = contract
+            .admin_reserve_range(0, 2, bob())
+            .iter()
+            .chain(
+                contract
+                    .nft_mint_many_ungaurded(SIZE - 2, &alice(), true, false)
+                    .iter(),
+            )
+            .map(|token| token.token_id.parse().unwrap())
+            .collect();
+        let provenance = contract.final_provenance();
+        assert_eq!(provenance, minted);
+        assert_eq!(
+            contract.final_provenance_page(5, 10),
+            minted[5..15].to_vec()
+        );
+        assert_eq!(
+            contract.final_provenance_page(15, 10),
+            minted[15..].to_vec()
+        );
+        assert!(contract.final_provenance_page(SIZE, 10).is_empty());
+
+        minted.sort_unstable();
+        assert_eq!(minted, (0..SIZE).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Provenance is final once sold out")]
+    fn final_provenance_before_sold_out() {
+        let mut contract = new_contract();
+        contract.nft_mint_many_ungaurded(1, &alice(), true, false);
+        contract.final_provenance();
+    }
+
+    #[test]
+    fn mint_live() {
+        let mut contract = new_contract();
+        assert!(contract.mint_live());
+
+        contract.sale.public_sale_start = None;
+        assert!(!contract.mint_live());
+
+        contract.sale.presale_start = Some(0);
+        assert!(contract.mint_live());
+
+        contract.sale.presale_supply_cap = Some(2);
+        contract.presale_minted = 2;
+        assert!(!contract.mint_live());
+
+        contract.sale.public_sale_start = Some(0);
+        assert!(contract.mint_live());
+        contract.frozen = true;
+        assert!(!contract.mint_live());
+        contract.frozen = false;
+        contract.nft_mint_many_ungaurded(SIZE, &owner(), true, false);
+        assert!(!contract.mint_live());
+    }
+
+    #[test]
+    fn effective_batch_limit() {
+        let mut sale = open_sale();
+        sale.public_sale_start = None;
+        sale.presale_start = Some(0);
+        sale.mint_rate_limit = Some(5);
+        let mut contract = new_contract_with_sale(sale);
+        assert_eq!(contract.effective_batch_limit(), 5);
+
+        contract.sale.presale_supply_cap = Some(3);
+        assert_eq!(contract.effective_batch_limit(), 3);
+        contract.presale_minted = 3;
+        assert_eq!(contract.effective_batch_limit(), 0);
+        contract.sale.presale_supply_cap = None;
+
+        contract.sale.presale_start = None;
+        assert_eq!(contract.effective_batch_limit(), 0);
+
+        contract.sale.public_sale_start = Some(0);
+        contract.sale.mint_rate_limit = None;
+        assert_eq!(contract.effective_batch_limit(), SIZE);
+        contract.frozen = true;
+        assert_eq!(contract.effective_batch_limit(), 0);
+        contract.frozen = false;
+
+        contract.sale.mint_rate_limit = Some(20);
+        contract.nft_mint_many_ungaurded(SIZE - 3, &owner(), true, false);
+        assert_eq!(contract.effective_batch_limit(), 3);
+
+        contract.nft_mint_many_ungaurded(3, &owner(), true, false);
+        assert_eq!(contract.effective_batch_limit(), 0);
+    }
+}
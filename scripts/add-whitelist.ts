@@ -1,4 +1,4 @@
-import { Gas } from "near-units";
+import { NEAR, Gas } from "near-units";
 import { readFile } from "fs/promises";
 import { Context } from "near-cli/context";
 import { Contract } from "..";
@@ -52,7 +52,10 @@ export async function main({ account, argv }: Context) {
     const gas = Gas.parse("250 Tgas");
     if (accounts.length > 0) {
       try {
-        await contract.add_whitelist_accounts({ accounts, allowance }, { gas });
+        await contract.add_whitelist_accounts(
+          { accounts, allowance },
+          { gas, attachedDeposit: NEAR.from("1") }
+        );
       } catch (e) {
         console.log(`Failed ${accounts}`);
         continue;
@@ -22,6 +22,7 @@ const sale: tenk.Sale = {
   public_sale_start: Date.now(),
   // initial_royalties: {
   //   percent: 10_000,
+  //   allow_zero_owner_remainder: true,
   //   accounts: {
   //     "tenk.sputnik-dao.near": 2_000,
   //     "project.sputnik-dao.near": 2_000,
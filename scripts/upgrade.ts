@@ -0,0 +1,44 @@
+import { NEAR, Gas } from "near-units";
+import { readFile } from "fs/promises";
+import { Context } from "near-cli/context";
+import { binPath } from "./utils";
+
+// Upgrade a contract deployed with the first version of the state layout.
+// The new code and the call to `migrate` go in the same transaction, so the contract is never
+// left with code that can't read its state. If `migrate` fails, the deploy is reverted too.
+export async function main({ account, nearAPI, argv }: Context) {
+  const { transactions } = nearAPI;
+  const contractBytes = await readFile(binPath("tenk"));
+
+  let [contractId] = argv ?? [];
+  contractId = contractId ?? account.accountId;
+  const isTestnet = contractId.endsWith("testnet");
+
+  const tx = account
+    .createTransaction(contractId)
+    .deployContract(contractBytes);
+  tx.actions.push(
+    transactions.functionCall(
+      "migrate",
+      {},
+      Gas.parse("300 Tgas"),
+      NEAR.from(0)
+    )
+  );
+  let res = await tx.signAndSend();
+  console.log(
+    `https://explorer${isTestnet ? ".testnet" : ""}.near.org/transactions/${
+      res.transaction_outcome.id
+    }`
+  );
+  //@ts-ignore
+  if (res.status.SuccessValue != undefined) {
+    console.log(`upgraded ${contractId}`);
+    console.log(
+      "call admin_index_raffle until it returns 0 before reserving specific ids"
+    );
+    console.log("call admin_count_holders until it returns true");
+  } else {
+    console.log(res);
+  }
+}